@@ -0,0 +1,318 @@
+use std::f32::consts::{PI, TAU};
+use std::ops::Range;
+
+use nannou::color::{rgba, Rgba};
+use nannou::geom::{vec2, Rect, Vec2};
+use nannou::rand::random_range;
+use nannou::Draw;
+
+use crate::field::{Field, Flow};
+
+/// Which way an agent with an angle of 0 heads. The first dailies went right (x follows the
+/// cosine), the later ones up (x follows the sine); the fields look different either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Heading {
+    #[default]
+    Right,
+    Up,
+}
+
+impl Heading {
+    /// The angle that heads along `x`, `y`.
+    pub fn angle_of(self, x: f64, y: f64) -> f32 {
+        match self {
+            Heading::Right => y.atan2(x) as f32,
+            Heading::Up => x.atan2(y) as f32,
+        }
+    }
+}
+
+/// How a field's value becomes an angle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Angle {
+    /// The noise times the strength, so agents follow the field's slopes in long curves.
+    #[default]
+    Direct,
+    /// The noise blown up and wrapped to 0..1 before the strength, which breaks the field into
+    /// tight cells.
+    Wrapped,
+}
+
+/// How far past the window an agent goes before it wraps around to the other side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Margin {
+    Fixed(f32),
+    /// Its own step size, so big agents are fully off screen before they jump.
+    StepSize,
+}
+
+/// Where in the noise field the agents look up their angles this frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Steering {
+    pub noise_scale: f64,
+    pub noise_strength: f64,
+    /// a z every agent shares, instead of each drifting through its own
+    pub z: Option<f64>,
+    /// how far the field has scrolled down the window, in noise units
+    pub scroll: f64,
+    pub angle: Angle,
+}
+
+impl Steering {
+    pub fn new(noise_scale: f64, noise_strength: f64) -> Steering {
+        Steering {
+            noise_scale,
+            noise_strength,
+            z: None,
+            scroll: 0.0,
+            angle: Angle::Direct,
+        }
+    }
+}
+
+pub struct Agent {
+    pub vector: Vec2,
+    pub vector_old: Vec2,
+    /// how big it's drawn
+    pub step_size: f32,
+    /// how far it moves per step
+    pub speed: f32,
+    pub angle: f32,
+    pub noise_z: f64,
+}
+
+impl Agent {
+    /// Somewhere in `bounds`, facing `angle` until something steers it.
+    pub fn new(bounds: Rect, noise_z_range: f64, step_size: Range<f32>, angle: f32) -> Agent {
+        let vector = vec2(
+            random_range(bounds.left(), bounds.right()),
+            random_range(bounds.top(), bounds.bottom()),
+        );
+        let step_size = random_range(step_size.start, step_size.end);
+        Agent {
+            vector,
+            vector_old: vector,
+            step_size,
+            speed: step_size,
+            angle,
+            noise_z: random_range(0.0, noise_z_range),
+        }
+    }
+
+    /// Turn to wherever `field` says at this agent's place in it. `heading` is the swarm's, so a
+    /// direction comes out the same either way.
+    pub fn steer(&mut self, field: &impl Field, steering: &Steering, heading: Heading) {
+        let flow = field.flow([
+            self.vector.x as f64 / steering.noise_scale,
+            self.vector.y as f64 / steering.noise_scale - steering.scroll,
+            steering.z.unwrap_or(self.noise_z),
+        ]);
+        self.angle = match (flow, steering.angle) {
+            (Flow::Direction(x, y), _) => heading.angle_of(x, y),
+            (Flow::Value(n), Angle::Direct) => (n * steering.noise_strength) as f32,
+            (Flow::Value(n), Angle::Wrapped) => {
+                let n = (n * 24.0) as f32;
+                (n - n.floor()) * steering.noise_strength as f32
+            }
+        };
+    }
+
+    /// Move one step along the current angle, scaled by `speed` (negative goes backwards).
+    pub fn step(&mut self, speed: f32, heading: Heading, margin: Margin, bounds: Rect) {
+        self.vector_old = self.vector;
+
+        let (x, y) = match heading {
+            Heading::Right => (self.angle.cos(), self.angle.sin()),
+            Heading::Up => (self.angle.sin(), self.angle.cos()),
+        };
+        self.vector += vec2(x, y) * self.speed * speed;
+
+        let margin = match margin {
+            Margin::Fixed(margin) => margin,
+            Margin::StepSize => self.step_size,
+        };
+        if self.vector.x < bounds.left() - margin {
+            self.vector.x = bounds.right() + margin;
+            self.vector_old.x = self.vector.x;
+        }
+        if self.vector.x > bounds.right() + margin {
+            self.vector.x = bounds.left() - margin;
+            self.vector_old.x = self.vector.x;
+        }
+        if self.vector.y < bounds.bottom() - margin {
+            self.vector.y = bounds.top() + margin;
+            self.vector_old.y = self.vector.y;
+        }
+        if self.vector.y > bounds.top() + margin {
+            self.vector.y = bounds.bottom() - margin;
+            self.vector_old.y = self.vector.y;
+        }
+    }
+}
+
+/// A crowd of agents wandering a noise field, the engine under most of the dailies.
+pub struct Swarm {
+    pub agents: Vec<Agent>,
+    pub heading: Heading,
+    pub margin: Margin,
+}
+
+impl Swarm {
+    /// `count` agents scattered over `bounds`, each with its own z somewhere in
+    /// `0..noise_z_range` and a step size somewhere in `step_size`, all facing angle 0.
+    pub fn new(count: usize, bounds: Rect, noise_z_range: f64, step_size: Range<f32>) -> Swarm {
+        Swarm {
+            agents: (0..count)
+                .map(|_| Agent::new(bounds, noise_z_range, step_size.clone(), 0.0))
+                .collect(),
+            heading: Heading::default(),
+            margin: Margin::Fixed(10.0),
+        }
+    }
+
+    pub fn with_heading(self, heading: Heading) -> Swarm {
+        Swarm { heading, ..self }
+    }
+
+    pub fn with_margin(self, margin: Margin) -> Swarm {
+        Swarm { margin, ..self }
+    }
+
+    /// Give every agent its own speed somewhere in `speed`, instead of moving by its step size.
+    pub fn with_speed(mut self, speed: Range<f32>) -> Swarm {
+        for agent in &mut self.agents {
+            agent.speed = random_range(speed.start, speed.end);
+        }
+        self
+    }
+
+    /// Face every agent a different way to start with.
+    pub fn with_random_angles(mut self) -> Swarm {
+        for agent in &mut self.agents {
+            agent.angle = random_range(0.0, TAU);
+        }
+        self
+    }
+
+    pub fn steer(&mut self, field: &impl Field, steering: &Steering) {
+        for agent in &mut self.agents {
+            agent.steer(field, steering, self.heading);
+        }
+    }
+
+    /// Move every agent a step, and drift their own noise z along by `noise_z_velocity`.
+    pub fn step(&mut self, speed: f32, noise_z_velocity: f64, bounds: Rect) {
+        for agent in &mut self.agents {
+            agent.noise_z += noise_z_velocity;
+            agent.step(speed, self.heading, self.margin, bounds);
+        }
+    }
+
+    /// A line over each agent's last step, `weight` times as thick as its step size.
+    pub fn draw_trails(&self, draw: &Draw, colour: Rgba, weight: f32) {
+        for agent in &self.agents {
+            draw.line()
+                .start(agent.vector_old)
+                .end(agent.vector)
+                .color(colour)
+                .stroke_weight(weight * agent.step_size);
+        }
+    }
+
+    /// A dot on each agent, `weight` times its step size across.
+    pub fn draw_dots(&self, draw: &Draw, colour: Rgba, weight: f32) {
+        for agent in &self.agents {
+            draw.ellipse()
+                .xy(agent.vector)
+                .radius(weight * agent.step_size / 2.0)
+                .color(colour);
+        }
+    }
+}
+
+/// The slowly cycling colour the agents are drawn in, `secs` into the piece.
+pub fn drifting_colour(secs: f32, alpha: f32) -> Rgba {
+    rgba(
+        (secs * 0.06 * PI + 3.0).sin().abs(),
+        (secs * 0.01 * PI + 6.0).sin().abs(),
+        (secs * 0.1 * PI).sin().abs(),
+        alpha,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nannou::noise::Constant;
+
+    fn bounds() -> Rect {
+        Rect::from_w_h(100.0, 100.0)
+    }
+
+    #[test]
+    fn steps_along_the_heading() {
+        let mut swarm = Swarm::new(1, bounds(), 0.4, 1.0..3.0);
+        swarm.agents[0].vector = Vec2::ZERO;
+        swarm.agents[0].speed = 2.0;
+        swarm.agents[0].angle = 0.0;
+        swarm.step(1.0, 0.0, bounds());
+        assert!(swarm.agents[0].vector.distance(vec2(2.0, 0.0)) < 1e-3);
+
+        let mut swarm = swarm.with_heading(Heading::Up);
+        swarm.step(1.0, 0.0, bounds());
+        assert!(swarm.agents[0].vector.distance(vec2(2.0, 2.0)) < 1e-3);
+        assert!(swarm.agents[0].vector_old.distance(vec2(2.0, 0.0)) < 1e-3);
+    }
+
+    #[test]
+    fn wraps_around_past_the_margin() {
+        let mut swarm = Swarm::new(1, bounds(), 0.4, 1.0..1.0001).with_speed(20.0..20.0001);
+        swarm.agents[0].angle = 0.0;
+        swarm.agents[0].vector = vec2(55.0, 0.0);
+        swarm.step(1.0, 0.0, bounds());
+        assert!((swarm.agents[0].vector.x + 60.0).abs() < 1e-3);
+        // no streak across the screen
+        assert_eq!(swarm.agents[0].vector_old, swarm.agents[0].vector);
+
+        let mut swarm = swarm.with_margin(Margin::StepSize);
+        swarm.agents[0].vector = vec2(45.0, 0.0);
+        swarm.step(1.0, 0.0, bounds());
+        assert!((swarm.agents[0].vector.x + 51.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn turns_noise_into_angles() {
+        let mut swarm = Swarm::new(2, bounds(), 0.4, 1.0..2.0);
+        swarm.steer(&Constant::new(0.5), &Steering::new(100.0, 3.0));
+        assert!(swarm.agents.iter().all(|agent| agent.angle == 1.5));
+
+        let wrapped = Steering {
+            angle: Angle::Wrapped,
+            ..Steering::new(100.0, 2.0)
+        };
+        swarm.steer(&Constant::new(0.51), &wrapped);
+        assert!((swarm.agents[0].angle - 0.48).abs() < 1e-4);
+    }
+
+    // down and to the right, wherever you are
+    struct Slope;
+
+    impl Field for Slope {
+        fn flow(&self, _point: [f64; 3]) -> Flow {
+            Flow::Direction(3.0, -4.0)
+        }
+    }
+
+    #[test]
+    fn follows_directions_whichever_way_it_heads() {
+        for heading in [Heading::Right, Heading::Up] {
+            let mut swarm = Swarm::new(1, bounds(), 0.4, 1.0..1.0001)
+                .with_speed(5.0..5.0001)
+                .with_heading(heading);
+            swarm.agents[0].vector = Vec2::ZERO;
+            swarm.steer(&Slope, &Steering::new(100.0, 3.0));
+            swarm.step(1.0, 0.0, bounds());
+            assert!(swarm.agents[0].vector.distance(vec2(3.0, -4.0)) < 1e-3);
+        }
+    }
+}
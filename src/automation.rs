@@ -0,0 +1,405 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::osc::Control;
+use crate::tween::ease::Ease;
+
+/// What keyframe times count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Unit {
+    #[default]
+    Beats,
+    Seconds,
+}
+
+/// How a keyframe gets to the next one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    /// Stays put, then jumps.
+    Hold,
+    Ease(Ease),
+}
+
+impl Default for Interpolation {
+    fn default() -> Interpolation {
+        Interpolation::Ease(Ease::LINEAR)
+    }
+}
+
+impl fmt::Display for Interpolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Interpolation::Hold => f.write_str("hold"),
+            Interpolation::Ease(ease) => ease.fmt(f),
+        }
+    }
+}
+
+impl FromStr for Interpolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Interpolation, String> {
+        match s.trim() {
+            "hold" => Ok(Interpolation::Hold),
+            ease => Ok(Interpolation::Ease(ease.parse()?)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub time: f64,
+    pub value: f32,
+    pub interpolation: Interpolation,
+}
+
+impl Keyframe {
+    pub fn new(time: f64, value: f32, interpolation: Interpolation) -> Keyframe {
+        Keyframe {
+            time,
+            value,
+            interpolation,
+        }
+    }
+}
+
+/// Keyframes for one parameter, named by its OSC address so `Automation::apply` can drive it
+/// through the same controls TouchOSC does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lane {
+    pub address: String,
+    // kept in time order
+    keyframes: Vec<Keyframe>,
+}
+
+impl Lane {
+    pub fn new(address: &str) -> Lane {
+        Lane {
+            address: address.to_string(),
+            keyframes: Vec::new(),
+        }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Add a keyframe, replacing any that's already at its time.
+    pub fn insert(&mut self, keyframe: Keyframe) {
+        match self
+            .keyframes
+            .binary_search_by(|k| k.time.total_cmp(&keyframe.time))
+        {
+            Ok(i) => self.keyframes[i] = keyframe,
+            Err(i) => self.keyframes.insert(i, keyframe),
+        }
+    }
+
+    /// The value at `time`, holding the first and last keyframes' values before and after them.
+    /// `None` without any keyframes.
+    pub fn value_at(&self, time: f64) -> Option<f32> {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let from = match next {
+            0 => return self.keyframes.first().map(|k| k.value),
+            i => self.keyframes[i - 1],
+        };
+        let to = match self.keyframes.get(next) {
+            Some(to) => to,
+            None => return Some(from.value),
+        };
+        match from.interpolation {
+            Interpolation::Hold => Some(from.value),
+            Interpolation::Ease(ease) => {
+                let progress = (time - from.time) / (to.time - from.time);
+                Some(from.value + (to.value - from.value) * ease.apply(progress as f32))
+            }
+        }
+    }
+}
+
+/// Keyframed parameters for a whole piece, so it can evolve over a track without anyone riding
+/// the controls.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Automation {
+    pub unit: Unit,
+    /// once playback reaches the end of this it jumps back to the start, forever
+    pub loop_region: Option<(f64, f64)>,
+    pub lanes: Vec<Lane>,
+}
+
+impl Automation {
+    pub fn load(path: impl AsRef<Path>) -> Result<Automation, Box<dyn Error>> {
+        Ok(fs::read_to_string(path)?.parse()?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn lane(&self, address: &str) -> Option<&Lane> {
+        self.lanes.iter().find(|lane| lane.address == address)
+    }
+
+    /// The lane for `address`, made empty if there isn't one yet.
+    pub fn lane_mut(&mut self, address: &str) -> &mut Lane {
+        let index = match self.lanes.iter().position(|lane| lane.address == address) {
+            Some(index) => index,
+            None => {
+                self.lanes.push(Lane::new(address));
+                self.lanes.len() - 1
+            }
+        };
+        &mut self.lanes[index]
+    }
+
+    /// Where playback is `seconds` (or `beats`, depending on `unit`) in, once the loop region
+    /// has wrapped it around.
+    pub fn position(&self, seconds: f64, beats: f64) -> f64 {
+        let time = match self.unit {
+            Unit::Beats => beats,
+            Unit::Seconds => seconds,
+        };
+        match self.loop_region {
+            Some((start, end)) if end > start && time >= end => {
+                start + (time - start).rem_euclid(end - start)
+            }
+            _ => time,
+        }
+    }
+
+    pub fn value_at(&self, address: &str, position: f64) -> Option<f32> {
+        self.lane(address)?.value_at(position)
+    }
+
+    /// Set every value control with a lane on `model`. Controls without one are left alone.
+    pub fn apply<M>(&self, controls: &[Control<M>], model: &mut M, position: f64) {
+        for control in controls {
+            if let Some(value) = self.value_at(control.address(), position) {
+                control.set(model, value);
+            }
+        }
+    }
+}
+
+/// A header, then each lane's address followed by its keyframes:
+///
+/// ```text
+/// unit beats
+/// loop 0 64
+/// lane /flow/agent_alpha
+/// key 0 0.2 linear
+/// key 16 1 in-out cubic
+/// key 32 1 hold
+/// ```
+impl fmt::Display for Automation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = match self.unit {
+            Unit::Beats => "beats",
+            Unit::Seconds => "seconds",
+        };
+        writeln!(f, "unit {}", unit)?;
+        if let Some((start, end)) = self.loop_region {
+            writeln!(f, "loop {} {}", start, end)?;
+        }
+        for lane in &self.lanes {
+            writeln!(f, "lane {}", lane.address)?;
+            for key in &lane.keyframes {
+                writeln!(f, "key {} {} {}", key.time, key.value, key.interpolation)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads what `Display` writes. Words can be spaced however, a lane named twice carries on where
+/// it left off, and times and values have to be finite.
+impl FromStr for Automation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Automation, String> {
+        let mut automation = Automation::default();
+        // the lane keys go into. a lane named again picks up where it left off
+        let mut lane = None;
+
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, rest) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("expected `key value`, got {:?}", line))?;
+            let rest = rest.trim();
+            let number = |word: Option<&str>| -> Result<f64, String> {
+                let number: f64 = word
+                    .ok_or_else(|| format!("missing number in {:?}", line))?
+                    .parse()
+                    .map_err(|e| format!("bad number in {:?}: {}", line, e))?;
+                if !number.is_finite() {
+                    return Err(format!("{} in {:?} isn't finite", number, line));
+                }
+                Ok(number)
+            };
+            match key {
+                "unit" => {
+                    automation.unit = match rest {
+                        "beats" => Unit::Beats,
+                        "seconds" => Unit::Seconds,
+                        _ => return Err(format!("unknown unit {:?}", rest)),
+                    }
+                }
+                "loop" => {
+                    let mut words = rest.split_whitespace();
+                    automation.loop_region = Some((number(words.next())?, number(words.next())?));
+                }
+                "lane" => {
+                    automation.lane_mut(rest);
+                    lane = Some(rest);
+                }
+                "key" => {
+                    let mut words = rest.split_whitespace();
+                    let time = number(words.next())?;
+                    let value = number(words.next())? as f32;
+                    if !value.is_finite() {
+                        return Err(format!("value in {:?} is too big", line));
+                    }
+                    let interpolation = match words.collect::<Vec<_>>().join(" ") {
+                        interpolation if interpolation.is_empty() => Interpolation::default(),
+                        interpolation => interpolation.parse()?,
+                    };
+                    let lane = lane.ok_or_else(|| format!("{:?} isn't in a lane", line))?;
+                    automation
+                        .lane_mut(lane)
+                        .insert(Keyframe::new(time, value, interpolation));
+                }
+                _ => return Err(format!("unknown key {:?}", key)),
+            }
+        }
+
+        Ok(automation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tween::ease::Curve;
+
+    #[derive(Default)]
+    struct Model {
+        scale: f32,
+        alpha: f32,
+    }
+
+    fn automation() -> Automation {
+        let mut automation = Automation::default();
+        let scale = automation.lane_mut("/scale");
+        scale.insert(Keyframe::new(8.0, 100.0, Interpolation::Hold));
+        scale.insert(Keyframe::new(0.0, 50.0, Interpolation::default()));
+        scale.insert(Keyframe::new(
+            4.0,
+            60.0,
+            Interpolation::Ease(Ease::In(Curve::Quad)),
+        ));
+        automation
+    }
+
+    #[test]
+    fn interpolates_between_keyframes() {
+        let lane = automation().lane("/scale").unwrap().clone();
+        assert_eq!(lane.keyframes().len(), 3);
+        assert_eq!(lane.value_at(-1.0), Some(50.0));
+        assert_eq!(lane.value_at(2.0), Some(55.0));
+        assert_eq!(lane.value_at(6.0), Some(70.0));
+        assert_eq!(lane.value_at(20.0), Some(100.0));
+        assert_eq!(Lane::new("/empty").value_at(0.0), None);
+
+        // the same time again replaces it
+        let mut lane = lane;
+        lane.insert(Keyframe::new(8.0, 0.0, Interpolation::Hold));
+        assert_eq!(lane.keyframes().len(), 3);
+        assert_eq!(lane.value_at(8.0), Some(0.0));
+    }
+
+    #[test]
+    fn loops_around_the_region() {
+        let mut automation = automation();
+        automation.loop_region = Some((4.0, 12.0));
+        assert_eq!(automation.position(99.0, 3.0), 3.0);
+        assert_eq!(automation.position(99.0, 13.0), 5.0);
+        assert_eq!(
+            automation.value_at("/scale", automation.position(0.0, 22.0)),
+            Some(70.0)
+        );
+
+        automation.unit = Unit::Seconds;
+        assert_eq!(automation.position(20.0, 0.0), 4.0);
+    }
+
+    #[test]
+    fn drives_osc_controls() {
+        let controls = vec![
+            Control::value("/scale", |m: &Model| m.scale, |m, v| m.scale = v),
+            Control::value("/alpha", |m: &Model| m.alpha, |m, v| m.alpha = v),
+        ];
+        let mut model = Model {
+            scale: 0.0,
+            alpha: 0.5,
+        };
+        automation().apply(&controls, &mut model, 2.0);
+        assert_eq!(model.scale, 55.0);
+        // no lane, so it's left alone
+        assert_eq!(model.alpha, 0.5);
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let mut automation = automation();
+        automation.loop_region = Some((0.0, 64.0));
+        automation.lane_mut("/alpha").insert(Keyframe::new(
+            1.5,
+            0.25,
+            Interpolation::Ease(Ease::Bezier(0.42, 0.0, 0.58, 1.0)),
+        ));
+        let text = automation.to_string();
+        assert_eq!(text.parse(), Ok(automation));
+
+        let written = "# a comment\nunit seconds\nlane /a\nkey 0 1\nkey 2 3 out back\n";
+        let parsed: Automation = written.parse().unwrap();
+        assert_eq!(parsed.unit, Unit::Seconds);
+        assert_eq!(parsed.value_at("/a", 0.0), Some(1.0));
+        assert!("key 0 1".parse::<Automation>().is_err());
+        assert!("unit bars".parse::<Automation>().is_err());
+        assert!("lane /a\nkey zero 1".parse::<Automation>().is_err());
+    }
+
+    #[test]
+    fn reads_hand_written_files() {
+        // spacing as it comes, and a lane split in two
+        let written = "lane /a\nkey 0  1\nlane /b\nkey 0 5\nlane /a\nkey 4 2   in-out  cubic\n";
+        let parsed: Automation = written.parse().unwrap();
+        assert_eq!(parsed.lanes.len(), 2);
+        let a = parsed.lane("/a").unwrap();
+        assert_eq!(a.keyframes().len(), 2);
+        assert_eq!(
+            a.keyframes()[1].interpolation,
+            Interpolation::Ease(Ease::InOut(Curve::Cubic))
+        );
+
+        let tabbed: Automation = "unit\tseconds\nlane\t/a\nkey\t0\t1\tin\tquad\n".parse().unwrap();
+        assert_eq!(tabbed.unit, Unit::Seconds);
+        assert_eq!(
+            tabbed.lane("/a").unwrap().keyframes()[0].interpolation,
+            Interpolation::Ease(Ease::In(Curve::Quad))
+        );
+
+        assert!("lane /a\nkey NaN 1".parse::<Automation>().is_err());
+        assert!("lane /a\nkey 0 1 bezier 0 nan 1 1".parse::<Automation>().is_err());
+        assert!("lane /a\nkey 0 inf".parse::<Automation>().is_err());
+        assert!("lane /a\nkey 0 1e300".parse::<Automation>().is_err());
+        assert!("loop 0 inf".parse::<Automation>().is_err());
+    }
+}
@@ -0,0 +1,57 @@
+// Work out the beat grid of a track ahead of time, for a sketch to play along to:
+//
+//     cargo run --release --bin beat_grid -- track.wav track.grid 3/4
+//
+// Leave off the output file (or pass `-`) to print the grid instead. The time signature defaults to 4/4.
+
+use std::env;
+use std::process;
+
+use dailies::beats::{analyze_file, BeatGrid};
+use dailies::tap::meter::Meter;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let input = match args.first() {
+        Some(input) => input,
+        None => {
+            eprintln!("usage: beat_grid <track.wav> [output.grid] [time signature]");
+            process::exit(1);
+        }
+    };
+
+    let meter = match args.get(2).map(|m| m.parse::<Meter>()) {
+        Some(Ok(meter)) => meter,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        None => Meter::default(),
+    };
+
+    let grid = match analyze_file(input, meter) {
+        Ok(grid) => grid,
+        Err(e) => {
+            eprintln!("couldn't find the beat in {}: {}", input, e);
+            process::exit(1);
+        }
+    };
+
+    eprintln!(
+        "{:.2}bpm, first downbeat at {:.3}s, {} beats",
+        grid.bpm,
+        grid.downbeat,
+        grid.beats.len()
+    );
+    match args.get(1).filter(|output| *output != "-") {
+        Some(output) => save(&grid, output),
+        None => print!("{}", grid),
+    }
+}
+
+fn save(grid: &BeatGrid, output: &str) {
+    if let Err(e) = grid.save(output) {
+        eprintln!("couldn't write {}: {}", output, e);
+        process::exit(1);
+    }
+}
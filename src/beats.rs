@@ -0,0 +1,451 @@
+// Beat tracking for tracks we have ahead of time, so the visuals can lock to them without
+// anyone tapping along.
+//
+// The track is turned into an onset envelope (spectral flux: how much louder each frequency got
+// since the last frame), the tempo comes from where that envelope best lines up with itself,
+// and then dynamic programming picks the beats that land on strong onsets while keeping close
+// to that tempo (Ellis, "Beat Tracking by Dynamic Programming", 2007). The downbeat is whichever
+// beat of the bar has the most going on in the bass.
+
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+use crate::tap::meter::Meter;
+use crate::tap::tempo;
+
+const WINDOW: usize = 1024;
+const HOP: usize = 512;
+// log compression, so quiet hits still count next to loud ones
+const COMPRESSION: f64 = 10.0;
+// kicks and bass live below this
+const LOW_BAND_HZ: f64 = 150.0;
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+// tempo guesses are biased towards this, an octave either side counting for less
+const PREFERRED_BPM: f64 = 120.0;
+const TEMPO_SPREAD_OCTAVES: f64 = 1.0;
+// how strongly beats stick to the tempo rather than chasing onsets
+const TIGHTNESS: f64 = 100.0;
+// beats at either end weaker than this (relative to the typical beat) are silence or intro
+const TRIM_THRESHOLD: f64 = 0.5;
+
+/// Where the beats are in a track, in seconds from the start of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatGrid {
+    pub bpm: f64,
+    /// the first "one" of a bar
+    pub downbeat: f64,
+    pub beats: Vec<f64>,
+}
+
+impl BeatGrid {
+    // index of the first downbeat in `beats`
+    fn downbeat_index(&self) -> usize {
+        self.beats
+            .iter()
+            .position(|&b| b >= self.downbeat - 1e-6)
+            .unwrap_or(0)
+    }
+
+    /// Beats since the first downbeat at `seconds` into the track; negative before it. Outside
+    /// the detected beats it carries on at the average tempo.
+    pub fn beats_at(&self, seconds: f64) -> f64 {
+        let beats_from = |index: usize, time: f64| index as f64 + (seconds - time) * self.bpm / 60.0;
+        let downbeat = self.downbeat_index() as f64;
+
+        let (first, last) = match (self.beats.first(), self.beats.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return beats_from(0, self.downbeat),
+        };
+        let beats = if seconds < first {
+            beats_from(0, first)
+        } else if seconds >= last {
+            beats_from(self.beats.len() - 1, last)
+        } else {
+            let next = self.beats.partition_point(|&b| b <= seconds);
+            let (start, end) = (self.beats[next - 1], self.beats[next]);
+            (next - 1) as f64 + (seconds - start) / (end - start)
+        };
+        beats - downbeat
+    }
+
+    /// The tempo between the beats either side of `seconds`.
+    pub fn bpm_at(&self, seconds: f64) -> f64 {
+        let next = self.beats.partition_point(|&b| b <= seconds);
+        match (next.checked_sub(1).map(|i| self.beats[i]), self.beats.get(next)) {
+            (Some(start), Some(&end)) if end > start => 60.0 / (end - start),
+            _ => self.bpm,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<BeatGrid, Box<dyn Error>> {
+        Ok(fs::read_to_string(path)?.parse()?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+}
+
+/// One `key value` pair per line, with a `beat` line for every beat.
+impl fmt::Display for BeatGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "bpm {:.3}", self.bpm)?;
+        writeln!(f, "downbeat {:.4}", self.downbeat)?;
+        for beat in &self.beats {
+            writeln!(f, "beat {:.4}", beat)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BeatGrid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BeatGrid, String> {
+        let mut bpm = None;
+        let mut downbeat = None;
+        let mut beats = Vec::new();
+
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("expected `key value`, got {:?}", line))?;
+            let value: f64 = value
+                .trim()
+                .parse()
+                .map_err(|e| format!("bad number in {:?}: {}", line, e))?;
+            if !value.is_finite() {
+                return Err(format!("bad number in {:?}", line));
+            }
+            match key {
+                "bpm" if value <= 0.0 => return Err(format!("bpm has to be above 0, got {}", value)),
+                "bpm" => bpm = Some(value),
+                "downbeat" => downbeat = Some(value),
+                "beat" => beats.push(value),
+                _ => return Err(format!("unknown key {:?}", key)),
+            }
+        }
+
+        Ok(BeatGrid {
+            bpm: bpm.ok_or("no bpm")?,
+            downbeat: downbeat.or(beats.first().copied()).unwrap_or(0.0),
+            beats,
+        })
+    }
+}
+
+/// Decode a WAV file down to mono samples and its sample rate.
+pub fn read_wav(path: impl AsRef<Path>) -> Result<(Vec<f32>, u32), Box<dyn Error>> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let full_scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / full_scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    let channels = spec.channels.max(1) as usize;
+    let mono = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    Ok((mono, spec.sample_rate))
+}
+
+pub fn analyze_file(path: impl AsRef<Path>, meter: Meter) -> Result<BeatGrid, Box<dyn Error>> {
+    let (samples, sample_rate) = read_wav(path)?;
+    analyze(&samples, sample_rate, meter).ok_or_else(|| "couldn't find a beat".into())
+}
+
+/// Find the beat grid of a mono track, or `None` if it's too short or has no beat to speak of.
+/// The meter says how many beats to look for a downbeat among.
+pub fn analyze(samples: &[f32], sample_rate: u32, meter: Meter) -> Option<BeatGrid> {
+    let (onsets, low_onsets) = onset_envelopes(samples, sample_rate);
+    let frame_rate = sample_rate as f64 / HOP as f64;
+    let period = beat_period(&onsets, frame_rate)?;
+
+    let frames = track_beats(&onsets, period);
+    if frames.len() < 2 {
+        return None;
+    }
+    // each frame stands for the middle of its window
+    let frame_time = |frame: usize| (frame * HOP + WINDOW / 2) as f64 / sample_rate as f64;
+    let beats: Vec<f64> = frames.iter().map(|&f| frame_time(f)).collect();
+
+    // the same fit as tapping, with the first beat as time zero
+    let times: Vec<f64> = beats.iter().map(|b| b - beats[0]).collect();
+    let bpm = tempo::estimate(&times).map_or(60.0 * frame_rate / period, |e| e.bpm());
+
+    let beats_per_bar = meter.beats_per_bar as usize;
+    let downbeat = (0..beats_per_bar.min(frames.len()))
+        .max_by(|&a, &b| {
+            let bass = |first: usize| -> f64 {
+                let hits: Vec<f64> = frames[first..]
+                    .iter()
+                    .step_by(beats_per_bar)
+                    .map(|&f| low_onsets[f])
+                    .collect();
+                hits.iter().sum::<f64>() / hits.len() as f64
+            };
+            bass(a).total_cmp(&bass(b))
+        })
+        .map_or(beats[0], |first| beats[first]);
+
+    Some(BeatGrid {
+        bpm,
+        downbeat,
+        beats,
+    })
+}
+
+// spectral flux across the whole spectrum and across just the bass
+fn onset_envelopes(samples: &[f32], sample_rate: u32) -> (Vec<f64>, Vec<f64>) {
+    let fft = FftPlanner::new().plan_fft_forward(WINDOW);
+    let hann: Vec<f64> = (0..WINDOW)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / WINDOW as f64).cos())
+        .collect();
+    let low_bins = ((LOW_BAND_HZ * WINDOW as f64 / sample_rate as f64).ceil() as usize).max(2);
+
+    let mut onsets = Vec::new();
+    let mut low_onsets = Vec::new();
+    let mut previous: Option<Vec<f64>> = None;
+    let mut buffer = vec![Complex::new(0.0, 0.0); WINDOW];
+
+    for start in (0..samples.len().saturating_sub(WINDOW - 1)).step_by(HOP) {
+        for (i, value) in buffer.iter_mut().enumerate() {
+            *value = Complex::new(samples[start + i] as f64 * hann[i], 0.0);
+        }
+        fft.process(&mut buffer);
+
+        let spectrum: Vec<f64> = buffer[..WINDOW / 2]
+            .iter()
+            .map(|c| (1.0 + COMPRESSION * c.norm()).ln())
+            .collect();
+        let rises: Vec<f64> = match &previous {
+            Some(previous) => spectrum
+                .iter()
+                .zip(previous)
+                .map(|(now, then)| (now - then).max(0.0))
+                .collect(),
+            None => vec![0.0; spectrum.len()],
+        };
+        onsets.push(rises.iter().sum());
+        // skip DC, which is mostly rumble and offsets
+        low_onsets.push(rises[1..low_bins].iter().sum());
+        previous = Some(spectrum);
+    }
+
+    (onsets, low_onsets)
+}
+
+// the beat length in frames, from the autocorrelation of the onset envelope
+fn beat_period(onsets: &[f64], frame_rate: f64) -> Option<f64> {
+    let mean = onsets.iter().sum::<f64>() / onsets.len().max(1) as f64;
+    let centered: Vec<f64> = onsets.iter().map(|o| o - mean).collect();
+
+    let min_lag = (60.0 * frame_rate / MAX_BPM).floor().max(1.0) as usize;
+    let max_lag = (60.0 * frame_rate / MIN_BPM).ceil() as usize;
+    if centered.len() <= max_lag + 1 {
+        return None;
+    }
+
+    let strength = |lag: usize| -> f64 {
+        let correlation = centered[lag..]
+            .iter()
+            .zip(&centered)
+            .map(|(a, b)| a * b)
+            .sum::<f64>()
+            / (centered.len() - lag) as f64;
+        let bpm = 60.0 * frame_rate / lag as f64;
+        let octaves = (bpm / PREFERRED_BPM).log2() / TEMPO_SPREAD_OCTAVES;
+        correlation * (-0.5 * octaves * octaves).exp()
+    };
+    let strengths: Vec<f64> = (min_lag - 1..=max_lag + 1).map(strength).collect();
+
+    let best = (1..strengths.len() - 1).max_by(|&a, &b| strengths[a].total_cmp(&strengths[b]))?;
+    if strengths[best] <= 0.0 {
+        return None;
+    }
+
+    // fit a parabola through the peak for a lag between frames
+    let (before, peak, after) = (strengths[best - 1], strengths[best], strengths[best + 1]);
+    let curvature = before - 2.0 * peak + after;
+    let shift = if curvature < 0.0 {
+        (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    Some((min_lag - 1 + best) as f64 + shift)
+}
+
+// the frames of the best chain of beats, each close to `period` after the last
+fn track_beats(onsets: &[f64], period: f64) -> Vec<usize> {
+    let spread = {
+        let mean = onsets.iter().sum::<f64>() / onsets.len().max(1) as f64;
+        let variance =
+            onsets.iter().map(|o| (o - mean).powi(2)).sum::<f64>() / onsets.len().max(1) as f64;
+        variance.sqrt()
+    };
+    if spread <= 0.0 {
+        return Vec::new();
+    }
+    let onsets: Vec<f64> = onsets.iter().map(|o| o / spread).collect();
+
+    let mut scores = vec![0.0; onsets.len()];
+    let mut previous = vec![None; onsets.len()];
+    let furthest = (2.0 * period).round() as usize;
+    let nearest = (period / 2.0).round().max(1.0) as usize;
+
+    for t in 0..onsets.len() {
+        let best = (t.saturating_sub(furthest)..t.saturating_sub(nearest))
+            .map(|p| {
+                let off_tempo = ((t - p) as f64 / period).ln();
+                (scores[p] - TIGHTNESS * off_tempo * off_tempo, p)
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        scores[t] = onsets[t];
+        if let Some((score, p)) = best {
+            if score > 0.0 {
+                scores[t] += score;
+                previous[t] = Some(p);
+            }
+        }
+    }
+
+    // the chain ends somewhere in the last beat
+    let tail = onsets.len().saturating_sub(period.ceil() as usize);
+    let mut beat = (tail..onsets.len()).max_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+    let mut frames = Vec::new();
+    while let Some(frame) = beat {
+        frames.push(frame);
+        beat = previous[frame];
+    }
+    frames.reverse();
+
+    // lose the beats the tracker carried on through silence at either end
+    let mut strengths: Vec<f64> = frames.iter().map(|&f| onsets[f]).collect();
+    strengths.sort_by(f64::total_cmp);
+    let typical = strengths.get(strengths.len() / 2).copied().unwrap_or(0.0);
+    let weak = |frame: &usize| onsets[*frame] < TRIM_THRESHOLD * typical;
+    let start = frames.iter().position(|f| !weak(f)).unwrap_or(frames.len());
+    let end = frames.iter().rposition(|f| !weak(f)).map_or(start, |i| i + 1);
+    frames[start..end].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 44100;
+
+    // a hi-hat tick on every beat and a kick on every downbeat, after a stretch of silence
+    fn click_track(bpm: f64, silence: f64, first_downbeat: usize, seconds: f64) -> Vec<f32> {
+        let rate = SAMPLE_RATE as f64;
+        let mut samples = vec![0.0_f32; (seconds * rate) as usize];
+        let interval = 60.0 / bpm;
+
+        let mut beat = 0;
+        while silence + beat as f64 * interval < seconds - 0.2 {
+            let start = ((silence + beat as f64 * interval) * rate) as usize;
+            let downbeat = beat >= first_downbeat && (beat - first_downbeat).is_multiple_of(4);
+            for i in 0..(0.15 * rate) as usize {
+                let t = i as f64 / rate;
+                let tick = (2.0 * PI * 3000.0 * t).sin() * (-t * 80.0).exp() * 0.3;
+                let kick = if downbeat {
+                    (2.0 * PI * 55.0 * t).sin() * (-t * 20.0).exp() * 0.8
+                } else {
+                    0.0
+                };
+                samples[start + i] += (tick + kick) as f32;
+            }
+            beat += 1;
+        }
+        samples
+    }
+
+    #[test]
+    fn finds_tempo_and_beats_of_a_click_track() {
+        let samples = click_track(120.0, 1.0, 0, 12.0);
+        let grid = analyze(&samples, SAMPLE_RATE, Meter::default()).unwrap();
+
+        assert!((grid.bpm - 120.0).abs() < 0.5, "bpm {}", grid.bpm);
+        // no beats in the silence at the start
+        assert!((grid.beats[0] - 1.0).abs() < 0.02, "first beat {}", grid.beats[0]);
+        for pair in grid.beats.windows(2) {
+            assert!((pair[1] - pair[0] - 0.5).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn finds_a_downbeat_after_a_pickup() {
+        let samples = click_track(128.0, 0.5, 1, 12.0);
+        let grid = analyze(&samples, SAMPLE_RATE, Meter::default()).unwrap();
+
+        let expected = 0.5 + 60.0 / 128.0;
+        assert!((grid.downbeat - expected).abs() < 0.02, "downbeat {}", grid.downbeat);
+        assert!((grid.bpm - 128.0).abs() < 0.5, "bpm {}", grid.bpm);
+    }
+
+    #[test]
+    fn silence_has_no_beat() {
+        let silence = vec![0.0; SAMPLE_RATE as usize * 5];
+        assert_eq!(analyze(&silence, SAMPLE_RATE, Meter::default()), None);
+        assert_eq!(analyze(&[0.0; 100], SAMPLE_RATE, Meter::default()), None);
+    }
+
+    #[test]
+    fn counts_beats_from_the_downbeat() {
+        let grid = BeatGrid {
+            bpm: 120.0,
+            downbeat: 1.0,
+            beats: vec![0.5, 1.0, 1.5, 2.1],
+        };
+        assert!((grid.beats_at(1.0)).abs() < 1e-9);
+        assert!((grid.beats_at(1.25) - 0.5).abs() < 1e-9);
+        assert!((grid.beats_at(1.8) - 1.5).abs() < 1e-9);
+        assert!((grid.bpm_at(1.8) - 100.0).abs() < 1e-9);
+        // before and after the detected beats, it keeps time at the average tempo
+        assert!((grid.beats_at(0.0) + 2.0).abs() < 1e-9);
+        assert!((grid.beats_at(3.1) - 4.0).abs() < 1e-9);
+        assert_eq!(grid.bpm_at(3.1), 120.0);
+    }
+
+    #[test]
+    fn grids_round_trip_through_text() {
+        let grid = BeatGrid {
+            bpm: 127.5,
+            downbeat: 0.25,
+            beats: vec![0.25, 0.7206, 1.1912],
+        };
+        assert_eq!(grid.to_string().parse::<BeatGrid>(), Ok(grid));
+
+        // lined up by hand, with spaces or tabs
+        let aligned: BeatGrid = "bpm      120\ndownbeat\t0.5\nbeat\t\t0.5\n".parse().unwrap();
+        assert_eq!((aligned.bpm, aligned.downbeat), (120.0, 0.5));
+        assert_eq!(aligned.beats, vec![0.5]);
+
+        assert!("downbeat 1.0".parse::<BeatGrid>().is_err());
+        assert!("bpm fast".parse::<BeatGrid>().is_err());
+        assert!("bpm 0".parse::<BeatGrid>().is_err());
+        assert!("bpm -120".parse::<BeatGrid>().is_err());
+        assert!("bpm inf".parse::<BeatGrid>().is_err());
+    }
+}
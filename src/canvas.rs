@@ -0,0 +1,154 @@
+use nannou::draw::{self, Draw};
+use nannou::frame::Frame;
+use nannou::wgpu::{self, util::DeviceExt};
+
+/// Somewhere off screen for a sketch to draw, so more than one can be on screen at once. Like a
+/// window it keeps what was drawn last frame unless the sketch sets a background, which is what
+/// trails rely on.
+pub struct Canvas {
+    // drawn into, multisampled like a window's frame is
+    msaa: wgpu::Texture,
+    // what the multisampled texture resolves to each frame, ready to be sampled
+    texture: wgpu::Texture,
+    renderer: draw::Renderer,
+}
+
+impl Canvas {
+    pub const SAMPLE_COUNT: u32 = Frame::DEFAULT_MSAA_SAMPLES;
+
+    /// A blank canvas `size` pixels big.
+    pub fn new(device: &wgpu::Device, size: [u32; 2]) -> Canvas {
+        let texture = |sample_count| {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .sample_count(sample_count)
+                .format(Frame::TEXTURE_FORMAT)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                )
+                .build(device)
+        };
+        let msaa = texture(Self::SAMPLE_COUNT);
+        let renderer =
+            draw::RendererBuilder::new().build_from_texture_descriptor(device, msaa.descriptor());
+        Canvas {
+            msaa,
+            texture: texture(1),
+            renderer,
+        }
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        self.texture.size()
+    }
+
+    /// What's been drawn so far.
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    /// Draw `draw` over what's already there. `scale_factor` is the window's, so points come out
+    /// the same size as they would on screen.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        draw: &Draw,
+        scale_factor: f32,
+    ) {
+        let msaa = self.msaa.view().build();
+        let texture = self.texture.view().build();
+        self.renderer.encode_render_pass(
+            device,
+            encoder,
+            draw,
+            scale_factor,
+            self.size(),
+            &msaa,
+            Some(&texture),
+        );
+    }
+}
+
+/// A shader run over the whole of its output that reads two canvases and a few bytes of
+/// uniforms, which is how transitions and layers put canvases together. The shader's entry
+/// points are `vs_main` and `fs_main`, and it binds the two textures, a sampler and the uniforms
+/// in that order.
+pub struct Pass {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl Pass {
+    /// Ready to draw into textures like `format`, multisampled `sample_count` times.
+    pub fn new(
+        device: &wgpu::Device,
+        shader: wgpu::ShaderModuleDescriptor,
+        sample_count: u32,
+        format: wgpu::TextureFormat,
+    ) -> Pass {
+        let sampler_desc = wgpu::SamplerBuilder::new().into_descriptor();
+        let filtering = wgpu::sampler_filtering(&sampler_desc);
+        let sampler = device.create_sampler(&sampler_desc);
+
+        let stage = wgpu::ShaderStages::FRAGMENT;
+        let dimension = wgpu::TextureViewDimension::D2;
+        let texture = wgpu::TextureSampleType::Float { filterable: true };
+        let bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+            .texture(stage, false, dimension, texture)
+            .texture(stage, false, dimension, texture)
+            .sampler(stage, filtering)
+            .uniform_buffer(stage, false)
+            .build(device);
+        let layout = wgpu::create_pipeline_layout(device, None, &[&bind_group_layout], &[]);
+
+        let shader = device.create_shader_module(shader);
+        let pipeline = wgpu::RenderPipelineBuilder::from_layout(&layout, &shader)
+            .vertex_entry_point("vs_main")
+            .fragment_shader(&shader)
+            .fragment_entry_point("fs_main")
+            .color_format(format)
+            .sample_count(sample_count)
+            .build(device);
+
+        Pass {
+            pipeline,
+            bind_group_layout,
+            sampler,
+        }
+    }
+
+    /// Run the shader over all of `output`. The textures needn't be the same size as it, they're
+    /// stretched to fit.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        textures: [&wgpu::Texture; 2],
+        uniforms: &[u8],
+        output: &wgpu::TextureView,
+    ) {
+        let uniforms = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("canvas pass uniforms"),
+            contents: uniforms,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let first = textures[0].view().build();
+        let second = textures[1].view().build();
+        let bind_group = wgpu::BindGroupBuilder::new()
+            .texture_view(&first)
+            .texture_view(&second)
+            .sampler(&self.sampler)
+            .buffer_bytes(&uniforms, 0, None)
+            .build(device, &self.bind_group_layout);
+
+        let mut pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(output, |color| color)
+            .begin(encoder);
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,272 @@
+// What the agents steer by. The dailies started out turning Perlin noise straight into an angle,
+// which is lovely but has sinks: places the field points into from every side, where agents pile
+// up and stay. Curl noise can't have those, and the other generators each give the trails a
+// different grain, so a sketch keeps a `Generator` and can swap between them while it runs.
+
+use std::fmt;
+use std::str::FromStr;
+
+use nannou::noise::{
+    Billow, Fbm, MultiFractal, NoiseFn, OpenSimplex, RidgedMulti, Seedable, Worley,
+};
+
+use crate::named;
+
+/// What a field says about one point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flow {
+    /// Somewhere around -1..1, for `Steering` to turn into an angle.
+    Value(f64),
+    /// Which way to go, as an x and y of any length. Steering's strength and angle mode don't
+    /// apply.
+    Direction(f64, f64),
+}
+
+/// Anything agents can be steered by, looked up at `[x, y, z]` in noise units. Every 3D noise
+/// function is one.
+pub trait Field {
+    fn flow(&self, point: [f64; 3]) -> Flow;
+}
+
+impl<N: NoiseFn<[f64; 3]>> Field for N {
+    fn flow(&self, point: [f64; 3]) -> Flow {
+        Flow::Value(self.get(point))
+    }
+}
+
+/// Flow along the contours of a noise function rather than by its value: its slope in x and y,
+/// turned a quarter. Nothing flows in without flowing back out, so agents trace streams and
+/// eddies without piling up.
+pub struct Curl<N> {
+    pub noise: N,
+}
+
+// how far either side the slope is measured, in noise units
+const STEP: f64 = 1e-4;
+
+impl<N: NoiseFn<[f64; 3]>> Field for Curl<N> {
+    fn flow(&self, [x, y, z]: [f64; 3]) -> Flow {
+        let dx = self.noise.get([x + STEP, y, z]) - self.noise.get([x - STEP, y, z]);
+        let dy = self.noise.get([x, y + STEP, z]) - self.noise.get([x, y - STEP, z]);
+        Flow::Direction(dy / (2.0 * STEP), -dx / (2.0 * STEP))
+    }
+}
+
+/// Which generator a `Generator` runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Kind {
+    /// What every daily was made with.
+    #[default]
+    Perlin,
+    /// Like Perlin, with fewer straight lines in it.
+    OpenSimplex,
+    /// Flat cells, so agents go straight and turn sharply at the edges.
+    Worley,
+    /// Perlin with finer and finer detail on top.
+    Fbm,
+    /// Sharp ridges, which agents bunch up along.
+    RidgedMulti,
+    /// Soft, puffy lumps.
+    Billow,
+    /// Perlin's curl.
+    Curl,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 7] = [
+        Kind::Perlin,
+        Kind::OpenSimplex,
+        Kind::Worley,
+        Kind::Fbm,
+        Kind::RidgedMulti,
+        Kind::Billow,
+        Kind::Curl,
+    ];
+
+    /// Where it is in `ALL`, so an OSC radio button can pick one by number.
+    pub fn index(self) -> usize {
+        Kind::ALL.iter().position(|&kind| kind == self).unwrap()
+    }
+
+    /// `ALL[index]`, or the last one past the end.
+    pub fn nth(index: usize) -> Kind {
+        Kind::ALL[index.min(Kind::ALL.len() - 1)]
+    }
+
+    /// `step` along `ALL` from this one, wrapping around either end.
+    pub fn step(self, step: isize) -> Kind {
+        let len = Kind::ALL.len() as isize;
+        Kind::ALL[(self.index() as isize + step).rem_euclid(len) as usize]
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Kind::Perlin => "perlin",
+            Kind::OpenSimplex => "opensimplex",
+            Kind::Worley => "worley",
+            Kind::Fbm => "fbm",
+            Kind::RidgedMulti => "ridged",
+            Kind::Billow => "billow",
+            Kind::Curl => "curl",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Kind, String> {
+        named::parse(&Kind::ALL, s, "field")
+    }
+}
+
+/// A field that can change generator or seed while the sketch runs.
+pub struct Generator {
+    kind: Kind,
+    seed: u32,
+    field: Box<dyn Field>,
+}
+
+impl Generator {
+    pub fn new(kind: Kind, seed: u32) -> Generator {
+        Generator {
+            kind,
+            seed,
+            field: build(kind, seed),
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn set_kind(&mut self, kind: Kind) {
+        if kind != self.kind {
+            *self = Generator::new(kind, self.seed);
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u32) {
+        if seed != self.seed {
+            *self = Generator::new(self.kind, seed);
+        }
+    }
+}
+
+impl Field for Generator {
+    fn flow(&self, point: [f64; 3]) -> Flow {
+        self.field.flow(point)
+    }
+}
+
+fn build(kind: Kind, seed: u32) -> Box<dyn Field> {
+    match kind {
+        Kind::Perlin => Box::new(perlin(seed)),
+        Kind::OpenSimplex => Box::new(OpenSimplex::new().set_seed(seed)),
+        Kind::Worley => Box::new(Worley::new().set_seed(seed)),
+        Kind::Fbm => Box::new(Fbm::new().set_seed(seed)),
+        Kind::RidgedMulti => Box::new(RidgedMulti::new().set_seed(seed)),
+        Kind::Billow => Box::new(Billow::new().set_seed(seed)),
+        Kind::Curl => Box::new(Curl {
+            noise: perlin(seed),
+        }),
+    }
+}
+
+/// Plain Perlin noise. noise 0.7 exports two different generators called `Perlin`, both through
+/// globs out of private modules, so naming either is ambiguous (and will stop compiling). A
+/// single octave of fBm is exactly the Perlin its fractals are built from.
+pub fn perlin(seed: u32) -> Fbm {
+    Fbm::new().set_octaves(1).set_seed(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> impl Iterator<Item = [f64; 3]> {
+        (0..100).map(|i| {
+            let i = i as f64;
+            [i * 0.37 + 0.1, i * 0.23 - 4.0, i * 0.011]
+        })
+    }
+
+    #[test]
+    fn curl_neither_gathers_nor_spreads() {
+        let curl = Curl { noise: perlin(7) };
+        let direction = |point| match curl.flow(point) {
+            Flow::Direction(x, y) => (x, y),
+            Flow::Value(_) => unreachable!(),
+        };
+        let h = 1e-2;
+        let (mut divergence, mut speed) = (0.0f64, 0.0f64);
+        for [x, y, z] in points() {
+            let (right, _) = direction([x + h, y, z]);
+            let (left, _) = direction([x - h, y, z]);
+            let (_, up) = direction([x, y + h, z]);
+            let (_, down) = direction([x, y - h, z]);
+            divergence = divergence.max(((right - left) + (up - down)).abs() / (2.0 * h));
+
+            let (x, y) = direction([x, y, z]);
+            speed = speed.max(x.hypot(y));
+        }
+        assert!(speed > 0.5);
+        assert!(
+            divergence < speed * 1e-2,
+            "{} against {}",
+            divergence,
+            speed
+        );
+    }
+
+    #[test]
+    fn every_kind_gives_something_to_steer_by() {
+        for kind in Kind::ALL {
+            let field = Generator::new(kind, 3);
+            let flows: Vec<Flow> = points().map(|point| field.flow(point)).collect();
+            assert!(
+                flows.windows(2).any(|pair| pair[0] != pair[1]),
+                "{} is flat",
+                kind
+            );
+            for flow in flows {
+                match flow {
+                    Flow::Value(value) => assert!(value.is_finite() && value.abs() < 4.0),
+                    Flow::Direction(x, y) => assert!(x.is_finite() && y.is_finite()),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reseeding_and_switching_change_the_field() {
+        let point = [1.3, 2.7, 0.4];
+        let mut field = Generator::new(Kind::Fbm, 1);
+        let before = field.flow(point);
+        field.set_seed(2);
+        assert_ne!(field.flow(point), before);
+        assert_eq!(field.kind(), Kind::Fbm);
+
+        field.set_kind(Kind::Curl);
+        assert!(matches!(field.flow(point), Flow::Direction(..)));
+        assert_eq!(field.seed(), 2);
+    }
+
+    #[test]
+    fn steps_and_reads_back_every_kind() {
+        assert_eq!(Kind::Perlin.step(1), Kind::OpenSimplex);
+        assert_eq!(Kind::Perlin.step(-1), Kind::Curl);
+        assert_eq!(Kind::Curl.step(1), Kind::Perlin);
+        assert_eq!(Kind::nth(Kind::Worley.index()), Kind::Worley);
+        assert_eq!(Kind::nth(99), Kind::Curl);
+
+        named::assert_reads_back(&Kind::ALL, "simplex");
+    }
+}
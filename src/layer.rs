@@ -0,0 +1,149 @@
+// Sketches stacked on top of each other, like `flow`'s agents over `9`'s noise trails. Every
+// sketch draws on its own canvas and `compositor` blends them together before they go on screen.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::named;
+
+pub mod compositor;
+
+/// How a layer's colours combine with what's under it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Blend {
+    /// Covers what's under it.
+    #[default]
+    Normal,
+    Add,
+    /// Only ever lightens, like two projectors on the same wall.
+    Screen,
+    /// Only ever darkens.
+    Multiply,
+    /// Black where the two are the same, bright where they differ.
+    Difference,
+}
+
+impl Blend {
+    pub const ALL: [Blend; 5] = [
+        Blend::Normal,
+        Blend::Add,
+        Blend::Screen,
+        Blend::Multiply,
+        Blend::Difference,
+    ];
+
+    /// The one after this in `ALL`, back to the start after the last.
+    pub fn next(self) -> Blend {
+        Blend::ALL[(self.index() as usize + 1) % Blend::ALL.len()]
+    }
+
+    /// Its place in `ALL`, which is the number `blend.wgsl` switches on.
+    pub fn index(self) -> u32 {
+        Blend::ALL.iter().position(|&blend| blend == self).unwrap() as u32
+    }
+}
+
+impl fmt::Display for Blend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Blend::Normal => "normal",
+            Blend::Add => "add",
+            Blend::Screen => "screen",
+            Blend::Multiply => "multiply",
+            Blend::Difference => "difference",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Blend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Blend, String> {
+        named::parse(&Blend::ALL, s, "blend mode")
+    }
+}
+
+/// How one layer goes over the ones under it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layer {
+    pub blend: Blend,
+    /// 0 is invisible, 1 is all the way there. Wherever the sketch hasn't drawn shows through
+    /// regardless.
+    pub opacity: f32,
+}
+
+impl Default for Layer {
+    fn default() -> Layer {
+        Layer {
+            blend: Blend::default(),
+            opacity: 1.0,
+        }
+    }
+}
+
+impl Layer {
+    pub fn new(blend: Blend, opacity: f32) -> Layer {
+        Layer { blend, opacity }
+    }
+
+    /// What `blend.wgsl` gets to draw it with: opacity, then the blend mode, padded out to 16
+    /// bytes.
+    pub fn uniforms(&self) -> [u8; 16] {
+        let mut uniforms = [0; 16];
+        uniforms[..4].copy_from_slice(&self.opacity.to_ne_bytes());
+        uniforms[4..8].copy_from_slice(&self.blend.index().to_ne_bytes());
+        uniforms
+    }
+
+    /// Opacity nudged by `by`, staying between 0 and 1.
+    pub fn fade(&mut self, by: f32) {
+        self.opacity = (self.opacity + by).clamp(0.0, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_uniforms_the_way_the_shader_reads_them() {
+        let uniforms = Layer::new(Blend::Multiply, 0.5).uniforms();
+        assert_eq!(uniforms[..4], 0.5f32.to_ne_bytes());
+        assert_eq!(uniforms[4..8], 3u32.to_ne_bytes());
+        assert_eq!(uniforms[8..], [0; 8]);
+
+        // the shader numbers the modes in the order it lists them
+        let shader = include_str!("layer/blend.wgsl");
+        let listed = shader
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("// `Blend::index`: "))
+            .unwrap();
+        let names: Vec<String> = Blend::ALL.iter().map(Blend::to_string).collect();
+        assert_eq!(listed, names.join(", "));
+        for blend in &Blend::ALL[1..] {
+            assert!(shader.contains(&format!("case {}u:", blend.index())));
+        }
+    }
+
+    #[test]
+    fn fades_between_nothing_and_all_the_way() {
+        let mut layer = Layer::new(Blend::Add, 1.0);
+        layer.fade(-2.0);
+        assert_eq!(layer.opacity, 0.0);
+        layer.fade(2.0);
+        assert_eq!(layer.opacity, 1.0);
+    }
+
+    #[test]
+    fn cycles_and_reads_back_every_mode() {
+        let mut blend = Blend::Normal;
+        for expected in Blend::ALL.iter().cycle().skip(1).take(Blend::ALL.len()) {
+            blend = blend.next();
+            assert_eq!(blend, *expected);
+        }
+        assert_eq!(blend, Blend::Normal);
+
+        named::assert_reads_back(&Blend::ALL, "overlay");
+    }
+}
@@ -0,0 +1,64 @@
+// One layer blended over what's under it, with the uniforms `Layer::uniforms` packs.
+
+struct Uniforms {
+    opacity: f32,
+    // `Blend::index`: normal, add, screen, multiply, difference
+    blend: u32,
+    _padding: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var base_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var layer_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var canvas_sampler: sampler;
+@group(0) @binding(3)
+var<uniform> uniforms: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// one triangle big enough to cover the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let x = f32((index << 1u) & 2u);
+    let y = f32(index & 2u);
+    var out: VertexOutput;
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.tex_coords = vec2<f32>(x, y);
+    return out;
+}
+
+fn blend(base: vec3<f32>, top: vec3<f32>) -> vec3<f32> {
+    switch uniforms.blend {
+        case 1u: {
+            return base + top;
+        }
+        case 2u: {
+            return 1.0 - (1.0 - base) * (1.0 - top);
+        }
+        case 3u: {
+            return base * top;
+        }
+        case 4u: {
+            return abs(base - top);
+        }
+        default: {
+            return top;
+        }
+    }
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let base = textureSample(base_texture, canvas_sampler, vertex.tex_coords).rgb;
+    let top = textureSample(layer_texture, canvas_sampler, vertex.tex_coords);
+    // drawing over a clear canvas leaves its colour multiplied by its alpha, so take that out
+    // before blending, then let the alpha through as coverage
+    let colour = select(vec3<f32>(0.0), top.rgb / top.a, top.a > 0.0);
+    let amount = top.a * uniforms.opacity;
+    return vec4<f32>(mix(base, blend(base, colour), amount), 1.0);
+}
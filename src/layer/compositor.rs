@@ -0,0 +1,86 @@
+use nannou::frame::Frame;
+use nannou::wgpu;
+
+use super::Layer;
+use crate::canvas::Pass;
+use crate::transition::{mixer::Mixer, Kind};
+
+/// Blends a stack of canvases into one, bottom to top, ready to go on screen.
+pub struct Compositor {
+    blend: Pass,
+    // for a bottom layer that's partway through a transition
+    mixer: Mixer,
+    // drawn into turn about, each pass reading what the last one left in the other
+    targets: Vec<wgpu::Texture>,
+}
+
+// what the next pass reads as its base
+#[derive(Clone, Copy)]
+enum Source {
+    Base,
+    Target(usize),
+}
+
+impl Compositor {
+    pub fn new(device: &wgpu::Device) -> Compositor {
+        let shader = wgpu::include_wgsl!("blend.wgsl");
+        Compositor {
+            blend: Pass::new(device, shader, 1, Frame::TEXTURE_FORMAT),
+            mixer: Mixer::new(device, 1, Frame::TEXTURE_FORMAT),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Put `layers` over `base` in order and return the result, which is `base` itself if
+    /// there's nothing to put over it. `from` is the canvas `base` is taking over from and how
+    /// far it's got, while there's a transition.
+    pub fn encode<'a>(
+        &'a mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        base: &'a wgpu::Texture,
+        from: Option<(&wgpu::Texture, Kind, f32)>,
+        layers: &[(&wgpu::Texture, Layer)],
+    ) -> &'a wgpu::Texture {
+        let size = base.size();
+        if self.targets.first().map(|target| target.size()) != Some(size) {
+            self.targets = (0..2).map(|_| target(device, size)).collect();
+        }
+
+        let mut source = Source::Base;
+        let mut next = 0;
+        if let Some((from, kind, progress)) = from {
+            let output = self.targets[next].view().build();
+            self.mixer
+                .encode(device, encoder, from, base, kind, progress, &output);
+            source = Source::Target(next);
+            next = 1 - next;
+        }
+
+        for (texture, layer) in layers {
+            let under = match source {
+                Source::Base => base,
+                Source::Target(index) => &self.targets[index],
+            };
+            let uniforms = layer.uniforms();
+            let output = self.targets[next].view().build();
+            self.blend
+                .encode(device, encoder, [under, texture], &uniforms, &output);
+            source = Source::Target(next);
+            next = 1 - next;
+        }
+
+        match source {
+            Source::Base => base,
+            Source::Target(index) => &self.targets[index],
+        }
+    }
+}
+
+fn target(device: &wgpu::Device, size: [u32; 2]) -> wgpu::Texture {
+    wgpu::TextureBuilder::new()
+        .size(size)
+        .format(Frame::TEXTURE_FORMAT)
+        .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+        .build(device)
+}
@@ -0,0 +1,16 @@
+
+pub mod agents;
+pub mod automation;
+pub mod beats;
+pub mod canvas;
+pub mod field;
+pub mod layer;
+pub mod midi;
+mod named;
+pub mod osc;
+pub mod sketch;
+pub mod sketches;
+pub mod sync;
+pub mod tap;
+pub mod transition;
+pub mod tween;
@@ -1,78 +1,438 @@
+// Runs any of the dailies, and switches between them without restarting:
+//
+//     cargo run --release -- tap_tempo_particle_flow track.grid track.automation
+//
+// Everything after the name goes to the sketch. Without a name it runs `flow`.
+//
+// Tab (or start + right stick click on a gamepad) moves on to the next sketch, shift-tab (or
+// start + left stick click) back to the one before. The sketches read the gamepad for themselves,
+// so it's buttons none of them use. Both keep running while one hands over to the other:
+// F1 cuts, F2 fades, F3 wipes through the incoming sketch's brightest parts first and F4
+// dissolves. F5 holds transitions for the next bar of the tap tempo and makes them last one.
+//
+// Other sketches can be stacked over the running one as layers. F6 adds the next sketch on top,
+// F7 takes the top one off, F8 changes how it blends and F9/F10 fade it out and in. Keys, the
+// mouse and OSC only ever reach the sketch at the bottom.
+//
+// F11 (shift-F11 back) changes the noise the running sketch's agents steer by.
+
+use std::cell::RefCell;
+use std::env;
+use std::mem;
+use std::process;
+
+use gilrs::{Button, EventType, Gilrs};
 use nannou::prelude::*;
-use gilrs::{Gilrs, Event, EventType};
+
+use dailies::automation::Unit;
+use dailies::canvas::Canvas;
+use dailies::layer::{compositor::Compositor, Blend, Layer};
+use dailies::osc;
+use dailies::sketch::{self, Entry, Sketch};
+use dailies::sketches;
+use dailies::transition::{mixer::Mixer, Kind, Transition};
+
+// how long a transition takes when it isn't lined up with the beat
+const TRANSITION_SECS: f64 = 2.0;
 
 fn main() {
-    nannou::app(data).update(update).run();
+    nannou::app(model).update(update).run();
 }
 
-pub struct Data {
-    r: f32,
-    g: f32,
-    b: f32,
-    x: f32,
-    y: f32,
-    gilrs: Gilrs,
+struct Model {
+    entries: Vec<Entry>,
+    current: usize,
+    running: Running,
+    // the sketch being left behind while a transition is under way
+    outgoing: Option<Running>,
+    transition: Option<Transition>,
+    // what the next switch will be
+    kind: Kind,
+    quantize: bool,
+    // stacked over `running`, bottom first
+    overlays: Vec<Overlay>,
+    mixer: Mixer,
+    compositor: RefCell<Compositor>,
+    args: Vec<String>,
+    // one server for every sketch, so switching doesn't fight over the port
+    osc: Option<osc::Server>,
+    gilrs: Option<Gilrs>,
 }
 
-fn data(app: &App) -> Data {
-    let gilrs = Gilrs::new().unwrap();
-    app.new_window()
-        .size(1920, 1080)
-        .view(draw)
-        .title("🔴")
+// a sketch and the canvas it draws on
+struct Running {
+    sketch: Box<dyn Sketch>,
+    canvas: RefCell<Option<Canvas>>,
+}
+
+impl Running {
+    fn new(sketch: Box<dyn Sketch>) -> Running {
+        Running {
+            sketch,
+            canvas: RefCell::new(None),
+        }
+    }
+
+    // Draw this frame onto its canvas, starting a new one if there isn't one yet or `resize` is
+    // set and it's the wrong size.
+    fn render(&self, app: &App, frame: &Frame, resize: bool) {
+        let device = frame.device_queue_pair().device();
+        let size = frame.texture_size();
+        let mut canvas = self.canvas.borrow_mut();
+        let stale = match &*canvas {
+            Some(canvas) => resize && canvas.size() != size,
+            None => true,
+        };
+        if stale {
+            *canvas = Some(Canvas::new(device, size));
+        }
+
+        let draw = Draw::new();
+        self.sketch.view(app, &draw);
+        let scale_factor = app.window(frame.window_id()).unwrap().scale_factor();
+        let mut encoder = frame.command_encoder();
+        canvas
+            .as_mut()
+            .unwrap()
+            .render(device, &mut encoder, &draw, scale_factor);
+    }
+}
+
+// a sketch stacked over the one that's running
+struct Overlay {
+    // where it is in `entries`
+    index: usize,
+    running: Running,
+    layer: Layer,
+}
+
+fn model(app: &App) -> Model {
+    let entries = sketches::all();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let name = if args.is_empty() {
+        sketches::DEFAULT.to_string()
+    } else {
+        args.remove(0)
+    };
+    let current = match sketch::find(&entries, &name) {
+        Some(current) => current,
+        None => {
+            eprintln!("no sketch called {:?}, try one of:", name);
+            for entry in &entries {
+                eprintln!("    {}", entry.name);
+            }
+            process::exit(1);
+        }
+    };
+
+    let entry = entries[current];
+    let window = app
+        .new_window()
+        .size(entry.size.0, entry.size.1)
+        .title(entry.name)
+        .view(view)
+        .key_pressed(key_pressed)
+        .key_released(key_released)
+        .mouse_pressed(mouse_pressed)
         .build()
         .unwrap();
+    let window = app.window(window).unwrap();
+    let mixer = Mixer::new(
+        window.device(),
+        window.msaa_samples(),
+        Frame::TEXTURE_FORMAT,
+    );
+    let compositor = RefCell::new(Compositor::new(window.device()));
 
+    // TouchOSC and friends can reach whatever the running sketch exposes
+    let osc = osc::Server::bind(osc::DEFAULT_PORT)
+        .map_err(|e| println!("no OSC server: {}", e))
+        .ok();
+    let gilrs = Gilrs::new()
+        .map_err(|e| println!("no gamepads: {}", e))
+        .ok();
 
-    Data {
-        r: 0.0,
-        g: 0.0,
-        b: 0.0,
-        x: 0.0,
-        y: 0.0,
+    Model {
+        entries,
+        current,
+        running: Running::new((entry.new)(app, &args)),
+        outgoing: None,
+        transition: None,
+        kind: Kind::default(),
+        quantize: false,
+        overlays: Vec::new(),
+        mixer,
+        compositor,
+        args,
+        osc,
         gilrs,
     }
 }
 
+fn switch(app: &App, model: &mut Model, step: isize) {
+    let current = sketch::step(&model.entries, model.current, step);
+    let entry = model.entries[current];
+    println!("{} to {}", model.kind, entry.name);
+
+    // only two at once: one already on its way out is gone
+    model.outgoing = None;
+    model.transition = None;
+
+    let window = app.main_window();
+    window.set_inner_size_points(entry.size.0 as f32, entry.size.1 as f32);
+    window.set_title(entry.name);
+
+    let mut incoming = Running::new((entry.new)(app, &model.args));
+    pick_up_the_beat(&*model.running.sketch, &mut *incoming.sketch);
+    let transition = model
+        .running
+        .sketch
+        .metro()
+        .filter(|_| model.quantize)
+        .and_then(|metro| Transition::on_the_bar_of(model.kind, metro))
+        .unwrap_or_else(|| {
+            Transition::new(model.kind, Unit::Seconds, app.time as f64, TRANSITION_SECS)
+        });
+    model.outgoing = Some(mem::replace(&mut model.running, incoming));
+    model.transition = Some(transition);
+    model.current = current;
+}
+
+// Start `to`'s tap tempo where `from`'s is, if they both keep one.
+fn pick_up_the_beat(from: &dyn Sketch, to: &mut dyn Sketch) {
+    if let (Some(from), Some(to)) = (from.metro(), to.metro_mut()) {
+        if let Some(bpm) = from.bpm {
+            to.sync(bpm, from.beats());
+        }
+    }
+}
+
+// Stack the sketch after the top one over everything else.
+fn add_layer(app: &App, model: &mut Model) {
+    let top = model
+        .overlays
+        .last()
+        .map_or(model.current, |overlay| overlay.index);
+    let index = sketch::step(&model.entries, top, 1);
+    let entry = model.entries[index];
+    let mut running = Running::new((entry.new)(app, &model.args));
+    pick_up_the_beat(&*model.running.sketch, &mut *running.sketch);
+    // screen rather than normal, so what's under it shows straight away
+    let layer = Layer::new(Blend::Screen, 1.0);
+    println!(
+        "layering {} on top, {} at {}",
+        entry.name, layer.blend, layer.opacity
+    );
+    model.overlays.push(Overlay {
+        index,
+        running,
+        layer,
+    });
+}
+
+// Change the top layer, if there is one.
+fn with_top_layer(model: &mut Model, change: impl FnOnce(&mut Layer)) {
+    match model.overlays.last_mut() {
+        Some(overlay) => {
+            change(&mut overlay.layer);
+            let name = model.entries[overlay.index].name;
+            let layer = overlay.layer;
+            println!("{} is {} at {:.1}", name, layer.blend, layer.opacity);
+        }
+        None => println!("there are no layers, F6 adds one"),
+    }
+}
+
+// Where `transition` has got to, in its own unit.
+fn now(app: &App, model: &Model, transition: &Transition) -> f64 {
+    match transition.unit {
+        Unit::Seconds => app.time as f64,
+        // a tempo that's been cleared isn't coming back in time, so it's over
+        Unit::Beats => {
+            let outgoing = model.outgoing.as_ref();
+            let running = &model.running;
+            [
+                running.sketch.metro(),
+                outgoing.and_then(|o| o.sketch.metro()),
+            ]
+            .into_iter()
+            .flatten()
+            .find(|metro| metro.bpm.is_some())
+            .map_or(f64::INFINITY, |metro| metro.visual_beats())
+        }
+    }
+}
 
-fn update(app: &App, data: &mut Data, frame_update: Update) {
-    while let Some(Event { id: _, event, time: _ }) = data.gilrs.next_event() {
-        match event {
-            EventType::ButtonPressed(button, _) => {
-                println!("Button pressed: {:?}", button);
+fn update(app: &App, model: &mut Model, frame_update: Update) {
+    if let Some(osc) = &model.osc {
+        for (message, from) in osc.receive() {
+            for reply in model.running.sketch.params(&message) {
+                osc.send(&reply, from);
             }
-            EventType::ButtonReleased(button, _) => {
-                println!("Button released: {:?}", button);
+        }
+    }
+
+    let mut step = 0;
+    if let Some(gilrs) = &mut model.gilrs {
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            if let EventType::ButtonPressed(button, _) = event {
+                if gilrs.gamepad(id).is_pressed(Button::Start) {
+                    match button {
+                        Button::RightThumb => step = 1,
+                        Button::LeftThumb => step = -1,
+                        _ => {}
+                    }
+                }
             }
-            EventType::AxisChanged(axis, value, _) => {
-                match axis {
-                    gilrs::Axis::RightStickX => {
-                        data.x = app.window_rect().right() * value * 0.666;
-                    },
-                    gilrs::Axis::RightStickY => {
-                        data.y = app.window_rect().top() * value * 0.666;
-                    },
-                    _ => {},
+        }
+    }
+    if step != 0 {
+        switch(app, model, step);
+    }
+
+    model.running.sketch.update(app, frame_update);
+    if let Some(outgoing) = &mut model.outgoing {
+        outgoing.sketch.update(app, frame_update);
+    }
+    for overlay in &mut model.overlays {
+        overlay.running.sketch.update(app, frame_update);
+    }
+
+    if let Some(transition) = model.transition {
+        if transition.is_done(now(app, model, &transition)) {
+            model.outgoing = None;
+            model.transition = None;
+        }
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    model.running.render(app, &frame, true);
+    let transition = match (&model.outgoing, &model.transition) {
+        (Some(outgoing), Some(transition)) => {
+            // it keeps the canvas it was drawn for, and gets stretched to fit if that's changed
+            outgoing.render(app, &frame, false);
+            let progress = transition.progress(now(app, model, transition));
+            Some((outgoing, transition.kind, progress))
+        }
+        _ => None,
+    };
+    for overlay in &model.overlays {
+        overlay.running.render(app, &frame, true);
+    }
+
+    let current = model.running.canvas.borrow();
+    let current = current.as_ref().unwrap().texture();
+    let outgoing = transition.map(|(outgoing, _, _)| outgoing.canvas.borrow());
+    let from = outgoing
+        .as_ref()
+        .zip(transition)
+        .map(|(canvas, (_, kind, progress))| (canvas.as_ref().unwrap().texture(), kind, progress));
+    let overlays: Vec<_> = model
+        .overlays
+        .iter()
+        .map(|overlay| overlay.running.canvas.borrow())
+        .collect();
+    let layers: Vec<_> = overlays
+        .iter()
+        .zip(&model.overlays)
+        .map(|(canvas, overlay)| (canvas.as_ref().unwrap().texture(), overlay.layer))
+        .collect();
+
+    let device = frame.device_queue_pair().device();
+    let mut encoder = frame.command_encoder();
+    if layers.is_empty() {
+        // nothing to stack, so straight on screen
+        let (from, kind, progress) = from.unwrap_or((current, Kind::Cut, 1.0));
+        model.mixer.encode(
+            device,
+            &mut encoder,
+            from,
+            current,
+            kind,
+            progress,
+            frame.texture_view(),
+        );
+    } else {
+        let mut compositor = model.compositor.borrow_mut();
+        let stack = compositor.encode(device, &mut encoder, current, from, &layers);
+        model.mixer.encode(
+            device,
+            &mut encoder,
+            stack,
+            stack,
+            Kind::Cut,
+            1.0,
+            frame.texture_view(),
+        );
+    }
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    match key {
+        Key::Tab if app.keys.mods.shift() => switch(app, model, -1),
+        Key::Tab => switch(app, model, 1),
+        Key::F1 | Key::F2 | Key::F3 | Key::F4 => {
+            model.kind = match key {
+                Key::F1 => Kind::Cut,
+                Key::F2 => Kind::Fade,
+                Key::F3 => Kind::LumaWipe,
+                _ => Kind::Dissolve,
+            };
+            println!("switching with a {}", model.kind);
+        }
+        Key::F5 => {
+            model.quantize = !model.quantize;
+            println!("on the bar: {}", model.quantize);
+        }
+        Key::F6 => add_layer(app, model),
+        Key::F7 => {
+            if let Some(overlay) = model.overlays.pop() {
+                println!("took {} off", model.entries[overlay.index].name);
+            }
+        }
+        Key::F8 => with_top_layer(model, |layer| layer.blend = layer.blend.next()),
+        Key::F9 => with_top_layer(model, |layer| layer.fade(-0.1)),
+        Key::F10 => with_top_layer(model, |layer| layer.fade(0.1)),
+        Key::F11 => {
+            let step = if app.keys.mods.shift() { -1 } else { 1 };
+            match model.running.sketch.field_mut() {
+                Some(field) => {
+                    field.set_kind(field.kind().step(step));
+                    println!("steering by {}", field.kind());
                 }
-                println!("Axis changed: {:?}", value);
+                None => println!("nothing in this sketch to steer"),
             }
-            _ => {}
         }
+        _ => model.running.sketch.key_pressed(app, key),
     }
+}
 
-    let t = frame_update.since_start.as_secs_f32();
-    let s = 0.333;
-    data.r = ((t * PI * 3.0 * s).sin() + 1.0)/2.0;
-    data.g = ((t * PI * 6.0 * s).sin() + 1.0)/2.0;
-    data.b = ((t * PI * 1.0 * s).sin() + 1.0)/2.0;
+fn key_released(app: &App, model: &mut Model, key: Key) {
+    if !is_launchers(key) {
+        model.running.sketch.key_released(app, key);
+    }
 }
 
-fn draw(app: &App, data: &Data, frame: Frame) {
-    let draw = app.draw();
-    draw.background().rgb(0.0, 0.0, 0.0);
-    draw.ellipse()
-        .x_y(data.x, data.y)
-        .rgb(data.r, data.g, data.b);
-    draw.to_frame(app, &frame).unwrap();
+// Keys the launcher keeps for itself rather than passing on to the sketch.
+fn is_launchers(key: Key) -> bool {
+    matches!(
+        key,
+        Key::Tab
+            | Key::F1
+            | Key::F2
+            | Key::F3
+            | Key::F4
+            | Key::F5
+            | Key::F6
+            | Key::F7
+            | Key::F8
+            | Key::F9
+            | Key::F10
+            | Key::F11
+    )
 }
 
+fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton) {
+    model.running.sketch.mouse_pressed(app, button);
+}
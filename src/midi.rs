@@ -0,0 +1,12 @@
+pub mod input;
+pub mod output;
+
+pub const CLOCKS_PER_BEAT: u32 = 24;
+// song position pointers count in sixteenth notes
+pub const CLOCKS_PER_SIXTEENTH: u32 = 6;
+
+pub const TIMING_CLOCK: u8 = 0xF8;
+pub const START: u8 = 0xFA;
+pub const CONTINUE: u8 = 0xFB;
+pub const STOP: u8 = 0xFC;
+pub const SONG_POSITION: u8 = 0xF2;
@@ -0,0 +1,320 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use midir::{Ignore, MidiInput, MidiInputConnection};
+
+use super::{
+    CLOCKS_PER_BEAT, CLOCKS_PER_SIXTEENTH, CONTINUE, SONG_POSITION, START, STOP, TIMING_CLOCK,
+};
+use crate::tap::clock::Clock;
+use crate::tap::Model;
+
+// two beats of clock is enough to smooth out jitter without lagging behind tempo changes
+const TEMPO_WINDOW: usize = 2 * CLOCKS_PER_BEAT as usize;
+// no clock for this long means the other end has gone away
+const CLOCK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Keeps track of tempo and song position from a stream of MIDI clock messages.
+#[derive(Debug, Default)]
+pub struct ClockFollower {
+    running: bool,
+    // clocks since the song started; the first clock after a start is clock zero
+    position: i64,
+    tick_times: VecDeque<Instant>,
+}
+
+impl ClockFollower {
+    pub fn new() -> Self {
+        ClockFollower::default()
+    }
+
+    pub fn handle(&mut self, message: &[u8], time: Instant) {
+        match message {
+            [TIMING_CLOCK, ..] => {
+                self.tick_times.push_back(time);
+                if self.tick_times.len() > TEMPO_WINDOW {
+                    self.tick_times.pop_front();
+                }
+                if self.running {
+                    self.position += 1;
+                }
+            }
+            [START, ..] => {
+                self.running = true;
+                self.position = -1;
+            }
+            [CONTINUE, ..] => self.running = true,
+            [STOP, ..] => self.running = false,
+            [SONG_POSITION, lsb, msb, ..] => {
+                let sixteenths = ((*msb as i64) << 7) | *lsb as i64;
+                self.position = sixteenths * CLOCKS_PER_SIXTEENTH as i64 - 1;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn bpm(&self) -> Option<f64> {
+        let first = self.tick_times.front()?;
+        let last = self.tick_times.back()?;
+        let seconds = last.duration_since(*first).as_secs_f64();
+        if self.tick_times.len() < 2 || seconds <= 0.0 {
+            return None;
+        }
+
+        let seconds_per_tick = seconds / (self.tick_times.len() - 1) as f64;
+        Some(60.0 / (seconds_per_tick * CLOCKS_PER_BEAT as f64))
+    }
+
+    /// Song position in beats at `time`, filling in between clocks at the current tempo.
+    pub fn beats_at(&self, time: Instant) -> Option<f64> {
+        if self.position < 0 {
+            return None;
+        }
+
+        let bpm = self.bpm()?;
+        let last_tick = self.tick_times.back()?;
+        let ticks_since = time.saturating_duration_since(*last_tick).as_secs_f64() * bpm / 60.0
+            * CLOCKS_PER_BEAT as f64;
+        // never run ahead of a clock that hasn't arrived yet
+        let ticks = self.position as f64 + ticks_since.min(1.0);
+        Some(ticks / CLOCKS_PER_BEAT as f64)
+    }
+
+    pub fn is_running(&self, time: Instant) -> bool {
+        let clock_alive = self
+            .tick_times
+            .back()
+            .is_some_and(|last| time.saturating_duration_since(*last) < CLOCK_TIMEOUT);
+        self.running && clock_alive
+    }
+}
+
+/// MIDI clock coming in from a drum machine or other software, driving a `tap::Model`.
+///
+/// While the clock runs it sets the metronome's tempo and beat phase; once it stops the
+/// metronome carries on at the last tempo and tapping takes over again.
+pub struct ClockInput {
+    _connection: MidiInputConnection<()>,
+    messages: Receiver<(Instant, Vec<u8>)>,
+    pub follower: ClockFollower,
+}
+
+impl ClockInput {
+    /// Open a virtual input port that other software (or `aconnect`) can send clock to.
+    #[cfg(unix)]
+    pub fn virtual_port(name: &str) -> Result<ClockInput, Box<dyn Error>> {
+        use midir::os::unix::VirtualInput;
+
+        let (sender, messages) = channel();
+        let connected = Instant::now();
+        let connection = midi_input(name)?.create_virtual(
+            name,
+            move |stamp, message, _| {
+                let time = stamp_time(connected, stamp, Instant::now());
+                let _ = sender.send((time, message.to_vec()));
+            },
+            (),
+        )?;
+
+        Ok(ClockInput::new(connection, messages))
+    }
+
+    /// Connect to the first input port whose name contains `name`.
+    pub fn connect(name: &str) -> Result<ClockInput, Box<dyn Error>> {
+        let input = midi_input(name)?;
+        let port = input
+            .ports()
+            .into_iter()
+            .find(|port| input.port_name(port).is_ok_and(|n| n.contains(name)))
+            .ok_or_else(|| format!("no MIDI input port matching {:?}", name))?;
+
+        let (sender, messages) = channel();
+        let connected = Instant::now();
+        let connection = input.connect(
+            &port,
+            name,
+            move |stamp, message, _| {
+                let time = stamp_time(connected, stamp, Instant::now());
+                let _ = sender.send((time, message.to_vec()));
+            },
+            (),
+        )?;
+
+        Ok(ClockInput::new(connection, messages))
+    }
+
+    fn new(connection: MidiInputConnection<()>, messages: Receiver<(Instant, Vec<u8>)>) -> Self {
+        ClockInput {
+            _connection: connection,
+            messages,
+            follower: ClockFollower::new(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.follower.is_running(Instant::now())
+    }
+
+    /// Read whatever clock has arrived and, if it's running, lock `metro` to it.
+    /// Call after the metronome's own update.
+    pub fn update<C: Clock>(&mut self, metro: &mut Model<C>) {
+        while let Ok((time, message)) = self.messages.try_recv() {
+            self.follower.handle(&message, time);
+        }
+
+        let now = Instant::now();
+        if !self.follower.is_running(now) {
+            return;
+        }
+        if let (Some(bpm), Some(beats)) = (self.follower.bpm(), self.follower.beats_at(now)) {
+            metro.sync(bpm, beats);
+        }
+    }
+}
+
+// midir stamps each message in microseconds from (on most backends) when the port was opened,
+// which is closer to when it was sent than when the callback got round to it
+fn stamp_time(connected: Instant, stamp: u64, arrived: Instant) -> Instant {
+    // a backend that counts from somewhere earlier gives times that haven't happened yet
+    connected
+        .checked_add(Duration::from_micros(stamp))
+        .map_or(arrived, |time| time.min(arrived))
+}
+
+fn midi_input(name: &str) -> Result<MidiInput, Box<dyn Error>> {
+    let mut input = MidiInput::new(name)?;
+    // clock and transport are system realtime messages, which aren't always let through
+    input.ignore(Ignore::None);
+    Ok(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a clock at `bpm` starting at `start`
+    fn ticks(follower: &mut ClockFollower, start: Instant, bpm: f64, count: usize) -> Instant {
+        let tick = Duration::from_secs_f64(60.0 / bpm / CLOCKS_PER_BEAT as f64);
+        let mut time = start;
+        for _ in 0..count {
+            follower.handle(&[TIMING_CLOCK], time);
+            time += tick;
+        }
+        time - tick
+    }
+
+    #[test]
+    fn measures_tempo_from_clock() {
+        let mut follower = ClockFollower::new();
+        assert_eq!(follower.bpm(), None);
+
+        ticks(&mut follower, Instant::now(), 128.0, 100);
+        assert!((follower.bpm().unwrap() - 128.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn start_counts_beats_from_zero() {
+        let mut follower = ClockFollower::new();
+        let start = Instant::now();
+        follower.handle(&[START], start);
+        assert_eq!(follower.beats_at(start), None);
+
+        let last = ticks(&mut follower, start, 120.0, 49);
+        assert!((follower.beats_at(last).unwrap() - 2.0).abs() < 1e-9);
+        assert!(follower.is_running(last));
+    }
+
+    #[test]
+    fn clock_without_start_only_gives_tempo() {
+        let mut follower = ClockFollower::new();
+        let last = ticks(&mut follower, Instant::now(), 120.0, 48);
+
+        assert!(follower.bpm().is_some());
+        assert!(!follower.is_running(last));
+    }
+
+    #[test]
+    fn continues_from_song_position() {
+        let mut follower = ClockFollower::new();
+        let start = Instant::now();
+        // sixteenth 20 is beat 5
+        follower.handle(&[SONG_POSITION, 20, 0], start);
+        follower.handle(&[CONTINUE], start);
+
+        let last = ticks(&mut follower, start, 120.0, 25);
+        assert!((follower.beats_at(last).unwrap() - 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stops_on_stop_or_silence() {
+        let mut follower = ClockFollower::new();
+        let start = Instant::now();
+        follower.handle(&[START], start);
+        let last = ticks(&mut follower, start, 120.0, 24);
+        assert!(follower.is_running(last));
+        assert!(!follower.is_running(last + CLOCK_TIMEOUT));
+
+        follower.handle(&[STOP], last);
+        assert!(!follower.is_running(last));
+    }
+
+    #[test]
+    fn drives_the_metronome() {
+        let mut follower = ClockFollower::new();
+        let start = Instant::now();
+        follower.handle(&[START], start);
+        let last = ticks(&mut follower, start, 140.0, 24 * 6 + 1);
+
+        let mut metro = Model::default();
+        metro.sync(follower.bpm().unwrap(), follower.beats_at(last).unwrap());
+        assert!((metro.bpm.unwrap() - 140.0).abs() < 0.01);
+        assert_eq!(metro.beat, 6);
+        assert_eq!(metro.bar, 1);
+    }
+
+    #[test]
+    fn times_clock_by_its_timestamps() {
+        let connected = Instant::now();
+        let arrived = connected + Duration::from_secs(1);
+        let stamp = |micros| stamp_time(connected, micros, arrived);
+        assert_eq!(stamp(250_000), connected + Duration::from_millis(250));
+        assert_eq!(stamp(5_000_000), arrived);
+        assert_eq!(stamp(u64::MAX), arrived);
+
+        // a beat of clock handled all at once still has its tempo
+        let mut follower = ClockFollower::new();
+        for i in 0..=CLOCKS_PER_BEAT as u64 {
+            let micros = i * 500_000 / CLOCKS_PER_BEAT as u64;
+            follower.handle(&[TIMING_CLOCK], stamp(micros));
+        }
+        assert!((follower.bpm().unwrap() - 120.0).abs() < 0.01);
+    }
+
+    #[test]
+    #[ignore = "needs an ALSA sequencer"]
+    fn receives_clock_on_a_virtual_port() {
+        use midir::MidiOutput;
+
+        let mut clock = ClockInput::virtual_port("dailies clock test").unwrap();
+        let output = MidiOutput::new("dailies clock test sender").unwrap();
+        let port = output
+            .ports()
+            .into_iter()
+            .find(|p| output.port_name(p).unwrap().contains("dailies clock test"))
+            .unwrap();
+        let mut connection = output.connect(&port, "sender").unwrap();
+
+        connection.send(&[START]).unwrap();
+        for _ in 0..48 {
+            connection.send(&[TIMING_CLOCK]).unwrap();
+            std::thread::sleep(Duration::from_secs_f64(0.5 / CLOCKS_PER_BEAT as f64));
+        }
+
+        let mut metro = Model::default();
+        clock.update(&mut metro);
+        assert!(clock.is_running());
+        assert!((metro.bpm.unwrap() - 120.0).abs() < 5.0);
+    }
+}
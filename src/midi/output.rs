@@ -0,0 +1,302 @@
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use midir::{MidiOutput, MidiOutputConnection};
+
+use super::{
+    CLOCKS_PER_BEAT, CLOCKS_PER_SIXTEENTH, CONTINUE, SONG_POSITION, START, STOP, TIMING_CLOCK,
+};
+use crate::tap::clock::Clock;
+use crate::tap::Model;
+
+// how often the sender thread wakes up to see if a clock is due; well under a clock at 300bpm
+const TICK_RESOLUTION: Duration = Duration::from_millis(1);
+// song position pointers are 14 bits of sixteenth notes
+const MAX_SONG_POSITION: i64 = 1 << 14;
+
+/// Works out which clock and transport messages bring a receiver to a given song position.
+#[derive(Debug, Default)]
+pub struct ClockGenerator {
+    // last clock sent, or None while stopped
+    position: Option<i64>,
+}
+
+impl ClockGenerator {
+    pub fn new() -> Self {
+        ClockGenerator::default()
+    }
+
+    /// The messages to send now that the song is at `beats`, or `None` once there's no tempo.
+    pub fn advance(&mut self, beats: Option<f64>) -> Vec<Vec<u8>> {
+        let mut messages = Vec::new();
+
+        let beats = match beats {
+            Some(beats) => beats.max(0.0),
+            None => {
+                if self.position.take().is_some() {
+                    messages.push(vec![STOP]);
+                }
+                return messages;
+            }
+        };
+
+        let tick = (beats * CLOCKS_PER_BEAT as f64).floor() as i64;
+        let in_step = self
+            .position
+            .is_some_and(|p| (tick - p).abs() <= CLOCKS_PER_BEAT as i64);
+
+        if !in_step {
+            if self.position.is_some() {
+                messages.push(vec![STOP]);
+            }
+            if tick == 0 {
+                messages.push(vec![START]);
+                self.position = Some(-1);
+            } else {
+                // jump to the sixteenth we're in and let the clocks below catch up from there
+                let sixteenth = tick / CLOCKS_PER_SIXTEENTH as i64;
+                let pointer = sixteenth % MAX_SONG_POSITION;
+                messages.push(vec![
+                    SONG_POSITION,
+                    (pointer & 0x7F) as u8,
+                    (pointer >> 7) as u8,
+                ]);
+                messages.push(vec![CONTINUE]);
+                self.position = Some(sixteenth * CLOCKS_PER_SIXTEENTH as i64 - 1);
+            }
+        }
+
+        if let Some(position) = self.position.as_mut() {
+            while *position < tick {
+                *position += 1;
+                messages.push(vec![TIMING_CLOCK]);
+            }
+        }
+
+        messages
+    }
+}
+
+#[derive(Debug, Default)]
+struct Transport {
+    bpm: Option<f64>,
+    // song position in beats at a point in time
+    anchor: Option<(Instant, f64)>,
+    offset: f64,
+    quit: bool,
+}
+
+impl Transport {
+    fn beats_at(&self, time: Instant) -> Option<f64> {
+        let bpm = self.bpm?;
+        let (anchor_time, anchor_beats) = self.anchor?;
+        let seconds = if time >= anchor_time {
+            time.duration_since(anchor_time).as_secs_f64()
+        } else {
+            -anchor_time.duration_since(time).as_secs_f64()
+        };
+        Some(anchor_beats + (seconds + self.offset) * bpm / 60.0)
+    }
+}
+
+/// Sends the metronome's tempo out as MIDI clock so other software and gear can follow it.
+///
+/// Clocks are sent from their own thread so they stay evenly spaced whatever the frame rate;
+/// the sketch only has to hand over the metronome's position once a frame.
+pub struct ClockOutput {
+    transport: Arc<Mutex<Transport>>,
+    sender: Option<JoinHandle<()>>,
+}
+
+impl ClockOutput {
+    /// Open a virtual output port that other software (or `aconnect`) can listen to.
+    #[cfg(unix)]
+    pub fn virtual_port(name: &str) -> Result<ClockOutput, Box<dyn Error>> {
+        use midir::os::unix::VirtualOutput;
+
+        let connection = MidiOutput::new(name)?.create_virtual(name)?;
+        Ok(ClockOutput::new(connection))
+    }
+
+    /// Connect to the first output port whose name contains `name`.
+    pub fn connect(name: &str) -> Result<ClockOutput, Box<dyn Error>> {
+        let output = MidiOutput::new(name)?;
+        let port = output
+            .ports()
+            .into_iter()
+            .find(|port| output.port_name(port).is_ok_and(|n| n.contains(name)))
+            .ok_or_else(|| format!("no MIDI output port matching {:?}", name))?;
+
+        let connection = output.connect(&port, name)?;
+        Ok(ClockOutput::new(connection))
+    }
+
+    fn new(mut connection: MidiOutputConnection) -> Self {
+        let transport = Arc::new(Mutex::new(Transport::default()));
+        let shared = Arc::clone(&transport);
+
+        let sender = thread::spawn(move || {
+            let mut generator = ClockGenerator::new();
+            loop {
+                let (beats, quit) = {
+                    let transport = shared.lock().unwrap();
+                    (transport.beats_at(Instant::now()), transport.quit)
+                };
+                let beats = if quit { None } else { beats };
+
+                for message in generator.advance(beats) {
+                    let _ = connection.send(&message);
+                }
+                if quit {
+                    break;
+                }
+                thread::sleep(TICK_RESOLUTION);
+            }
+        });
+
+        ClockOutput {
+            transport,
+            sender: Some(sender),
+        }
+    }
+
+    /// Send clock this many seconds ahead of the visuals, to make up for the delay in whatever
+    /// is listening. Negative values send it late.
+    pub fn set_offset(&self, seconds: f64) {
+        self.transport.lock().unwrap().offset = seconds;
+    }
+
+    pub fn offset(&self) -> f64 {
+        self.transport.lock().unwrap().offset
+    }
+
+    /// Hand over the metronome's tempo and position. Call after the metronome's own update.
+    pub fn update<C: Clock>(&self, metro: &Model<C>) {
+        let mut transport = self.transport.lock().unwrap();
+        transport.bpm = metro.bpm;
+        transport.anchor = Some((metro.clock.now(), metro.beats()));
+    }
+}
+
+impl Drop for ClockOutput {
+    fn drop(&mut self) {
+        self.transport.lock().unwrap().quit = true;
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::input::ClockFollower;
+
+    fn clocks(messages: &[Vec<u8>]) -> usize {
+        messages.iter().filter(|m| m[0] == TIMING_CLOCK).count()
+    }
+
+    #[test]
+    fn starts_from_the_top() {
+        let mut generator = ClockGenerator::new();
+        assert!(generator.advance(None).is_empty());
+
+        let messages = generator.advance(Some(0.0));
+        assert_eq!(messages, vec![vec![START], vec![TIMING_CLOCK]]);
+
+        let messages = generator.advance(Some(0.5));
+        assert_eq!(clocks(&messages), 12);
+        assert_eq!(messages.len(), 12);
+    }
+
+    #[test]
+    fn stops_when_the_tempo_goes() {
+        let mut generator = ClockGenerator::new();
+        generator.advance(Some(0.0));
+
+        assert_eq!(generator.advance(None), vec![vec![STOP]]);
+        assert!(generator.advance(None).is_empty());
+    }
+
+    #[test]
+    fn joins_mid_song_with_a_song_position() {
+        let mut generator = ClockGenerator::new();
+        // beat 5 and a bit, which is sixteenth 20
+        let messages = generator.advance(Some(5.1));
+        assert_eq!(messages[0], vec![SONG_POSITION, 20, 0]);
+        assert_eq!(messages[1], vec![CONTINUE]);
+        assert_eq!(clocks(&messages), 3);
+    }
+
+    #[test]
+    fn repositions_after_a_jump() {
+        let mut generator = ClockGenerator::new();
+        generator.advance(Some(0.0));
+        generator.advance(Some(1.0));
+
+        let messages = generator.advance(Some(16.0));
+        assert_eq!(messages[0], vec![STOP]);
+        assert_eq!(messages[1], vec![SONG_POSITION, 64, 0]);
+        assert_eq!(messages[2], vec![CONTINUE]);
+    }
+
+    #[test]
+    fn a_follower_ends_up_where_the_generator_is() {
+        let mut generator = ClockGenerator::new();
+        let mut follower = ClockFollower::new();
+        let start = Instant::now();
+        let tick = Duration::from_secs_f64(0.5 / CLOCKS_PER_BEAT as f64);
+
+        // join late, then run for a few beats at 120bpm
+        let mut time = start;
+        for step in 0..=96 {
+            // half a clock in, so rounding never lands on the wrong side of one
+            let beats = (72.5 + step as f64) / CLOCKS_PER_BEAT as f64;
+            for message in generator.advance(Some(beats)) {
+                follower.handle(&message, time);
+            }
+            time += tick;
+        }
+
+        let last = time - tick;
+        assert!(follower.is_running(last));
+        assert!((follower.beats_at(last).unwrap() - 7.0).abs() < 1e-9);
+        assert!((follower.bpm().unwrap() - 120.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn offset_sends_clock_early() {
+        let now = Instant::now();
+        let transport = Transport {
+            bpm: Some(120.0),
+            anchor: Some((now, 4.0)),
+            offset: 0.25,
+            quit: false,
+        };
+        assert!((transport.beats_at(now).unwrap() - 4.5).abs() < 1e-9);
+    }
+
+    #[test]
+    #[ignore = "needs an ALSA sequencer"]
+    fn sends_clock_on_a_virtual_port() {
+        use crate::midi::input::ClockInput;
+
+        let output = ClockOutput::virtual_port("dailies clock out test").unwrap();
+        let mut input = ClockInput::connect("dailies clock out test").unwrap();
+
+        let mut metro = Model::default();
+        metro.sync(120.0, 0.0);
+        for _ in 0..100 {
+            metro.update();
+            output.update(&metro);
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let mut follower = Model::default();
+        input.update(&mut follower);
+        assert!(input.is_running());
+        assert!((follower.bpm.unwrap() - 120.0).abs() < 5.0);
+    }
+}
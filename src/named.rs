@@ -0,0 +1,24 @@
+// Enums that get picked by name, over OSC, on the command line or in a file. Each lists every
+// value in an `ALL` and displays as its name, so parsing is finding the one with that name.
+
+use std::fmt::Display;
+
+/// Whichever of `all` displays as `s`. `what` says what they are when none does.
+pub fn parse<T: Copy + Display>(all: &[T], s: &str, what: &str) -> Result<T, String> {
+    all.iter()
+        .copied()
+        .find(|value| value.to_string() == s)
+        .ok_or_else(|| format!("unknown {} {:?}", what, s))
+}
+
+/// Every one of `all` parses back from its name, and `unknown` doesn't parse.
+#[cfg(test)]
+pub fn assert_reads_back<T>(all: &[T], unknown: &str)
+where
+    T: Copy + Display + std::str::FromStr + PartialEq + std::fmt::Debug,
+{
+    for &value in all {
+        assert_eq!(value.to_string().parse::<T>().ok(), Some(value));
+    }
+    assert!(unknown.parse::<T>().is_err());
+}
@@ -0,0 +1,441 @@
+// A small OSC-over-UDP server so TouchOSC layouts and other software can drive sketch parameters.
+//
+// Sketches describe what they expose as a list of `Control`s. Sending a value to a control's
+// address sets it, sending the address with no arguments asks for the current value, and
+// `/query` asks for every value at once. Replies go back to whoever asked.
+
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+
+/// TouchOSC sends to this port out of the box.
+pub const DEFAULT_PORT: u16 = 8000;
+
+const BUNDLE_TAG: &[u8] = b"#bundle\0";
+const QUERY_ADDRESS: &str = "/query";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Int(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    String(String),
+    Bool(bool),
+}
+
+impl Arg {
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Arg::Int(i) => Some(*i as f32),
+            Arg::Float(f) => Some(*f),
+            Arg::Long(i) => Some(*i as f32),
+            Arg::Double(d) => Some(*d as f32),
+            Arg::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            Arg::String(s) => s.parse().ok(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub address: String,
+    pub args: Vec<Arg>,
+}
+
+impl Message {
+    pub fn new(address: &str, args: Vec<Arg>) -> Self {
+        Message {
+            address: address.to_string(),
+            args,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_string(&mut bytes, &self.address);
+
+        let mut type_tags = String::from(",");
+        let mut arguments = Vec::new();
+        for arg in &self.args {
+            match arg {
+                Arg::Int(i) => {
+                    type_tags.push('i');
+                    arguments.extend_from_slice(&i.to_be_bytes());
+                }
+                Arg::Float(f) => {
+                    type_tags.push('f');
+                    arguments.extend_from_slice(&f.to_be_bytes());
+                }
+                Arg::Long(i) => {
+                    type_tags.push('h');
+                    arguments.extend_from_slice(&i.to_be_bytes());
+                }
+                Arg::Double(d) => {
+                    type_tags.push('d');
+                    arguments.extend_from_slice(&d.to_be_bytes());
+                }
+                Arg::String(s) => {
+                    type_tags.push('s');
+                    write_string(&mut arguments, s);
+                }
+                Arg::Bool(b) => type_tags.push(if *b { 'T' } else { 'F' }),
+            }
+        }
+
+        write_string(&mut bytes, &type_tags);
+        bytes.extend(arguments);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Message> {
+        let mut reader = Reader { bytes, position: 0 };
+        let address = reader.string()?;
+        if !address.starts_with('/') {
+            return None;
+        }
+
+        // very old senders leave the type tags off altogether
+        if reader.done() {
+            return Some(Message::new(&address, Vec::new()));
+        }
+
+        let type_tags = reader.string()?;
+        let mut args = Vec::new();
+        for tag in type_tags.strip_prefix(',')?.chars() {
+            let arg = match tag {
+                'i' => Arg::Int(i32::from_be_bytes(reader.take()?)),
+                'f' => Arg::Float(f32::from_be_bytes(reader.take()?)),
+                'h' => Arg::Long(i64::from_be_bytes(reader.take()?)),
+                'd' => Arg::Double(f64::from_be_bytes(reader.take()?)),
+                's' | 'S' => Arg::String(reader.string()?),
+                'T' => Arg::Bool(true),
+                'F' => Arg::Bool(false),
+                // nil, impulse and friends carry no data; anything else we can't skip safely
+                'N' | 'I' => continue,
+                _ => return None,
+            };
+            args.push(arg);
+        }
+
+        Some(Message { address, args })
+    }
+}
+
+/// Every message in a packet, unpacking bundles (and bundles in bundles).
+pub fn decode_packet(bytes: &[u8]) -> Vec<Message> {
+    let mut messages = Vec::new();
+    decode_into(bytes, &mut messages);
+    messages
+}
+
+fn decode_into(bytes: &[u8], messages: &mut Vec<Message>) {
+    if !bytes.starts_with(BUNDLE_TAG) {
+        messages.extend(Message::decode(bytes));
+        return;
+    }
+
+    // skip the tag and the time tag; everything is applied as soon as it arrives
+    let mut reader = Reader {
+        bytes,
+        position: BUNDLE_TAG.len() + 8,
+    };
+    while let Some(size) = reader.take::<4>().map(u32::from_be_bytes) {
+        match reader.slice(size as usize) {
+            Some(element) => decode_into(element, messages),
+            None => break,
+        }
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(s.as_bytes());
+    // null terminated, padded out to four bytes
+    let padding = 4 - s.len() % 4;
+    bytes.extend(std::iter::repeat_n(0, padding));
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn done(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn slice(&mut self, length: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.position..self.position + length)?;
+        self.position += length;
+        Some(slice)
+    }
+
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.slice(N)?.try_into().ok()
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let rest = self.bytes.get(self.position..)?;
+        let length = rest.iter().position(|b| *b == 0)?;
+        let s = std::str::from_utf8(&rest[..length]).ok()?.to_string();
+        self.position += (length / 4 + 1) * 4;
+        Some(s)
+    }
+}
+
+/// Something a sketch lets OSC reach.
+pub enum Control<M> {
+    /// A parameter that can be set and read back.
+    Value {
+        address: &'static str,
+        get: fn(&M) -> f32,
+        set: fn(&mut M, f32),
+    },
+    /// An action, like tapping the metronome.
+    Trigger {
+        address: &'static str,
+        run: fn(&mut M),
+    },
+}
+
+impl<M> Control<M> {
+    pub fn value(address: &'static str, get: fn(&M) -> f32, set: fn(&mut M, f32)) -> Self {
+        Control::Value { address, get, set }
+    }
+
+    pub fn trigger(address: &'static str, run: fn(&mut M)) -> Self {
+        Control::Trigger { address, run }
+    }
+
+    /// Set a value control. NaN and infinity are dropped before they can reach the sketch, and
+    /// triggers don't take values.
+    pub fn set(&self, model: &mut M, value: f32) {
+        if let Control::Value { set, .. } = self {
+            if value.is_finite() {
+                set(model, value);
+            }
+        }
+    }
+
+    pub fn address(&self) -> &'static str {
+        match self {
+            Control::Value { address, .. } | Control::Trigger { address, .. } => address,
+        }
+    }
+}
+
+/// Apply `message` to `model`, returning any replies.
+pub fn dispatch<M>(controls: &[Control<M>], model: &mut M, message: &Message) -> Vec<Message> {
+    if message.address == QUERY_ADDRESS {
+        return controls
+            .iter()
+            .filter_map(|control| match control {
+                Control::Value { address, get, .. } => {
+                    Some(Message::new(address, vec![Arg::Float(get(model))]))
+                }
+                Control::Trigger { .. } => None,
+            })
+            .collect();
+    }
+
+    let control = match controls.iter().find(|c| c.address() == message.address) {
+        Some(control) => control,
+        None => return Vec::new(),
+    };
+    let value = message.args.first().and_then(Arg::as_f32);
+
+    match (control, value) {
+        (Control::Value { address, get, .. }, None) => {
+            vec![Message::new(address, vec![Arg::Float(get(model))])]
+        }
+        (Control::Value { .. }, Some(value)) => {
+            control.set(model, value);
+            Vec::new()
+        }
+        // buttons send 1 on press and 0 on release; only the press counts
+        (Control::Trigger { run, .. }, value) => {
+            if value.is_none_or(|v| v != 0.0) {
+                run(model);
+            }
+            Vec::new()
+        }
+    }
+}
+
+pub struct Server {
+    socket: UdpSocket,
+}
+
+impl Server {
+    pub fn bind(port: u16) -> io::Result<Server> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Server { socket })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Everything that's arrived since the last call.
+    pub fn receive(&self) -> Vec<(Message, SocketAddr)> {
+        let mut received = Vec::new();
+        let mut buffer = [0; 4096];
+        while let Ok((length, from)) = self.socket.recv_from(&mut buffer) {
+            for message in decode_packet(&buffer[..length]) {
+                received.push((message, from));
+            }
+        }
+        received
+    }
+
+    pub fn send(&self, message: &Message, to: SocketAddr) {
+        if let Err(e) = self.socket.send_to(&message.encode(), to) {
+            println!("OSC send to {} failed: {}", to, e);
+        }
+    }
+
+    /// Apply whatever has arrived to `model` and answer any queries. Call once a frame.
+    pub fn update<M>(&self, controls: &[Control<M>], model: &mut M) {
+        for (message, from) in self.receive() {
+            for reply in dispatch(controls, model, &message) {
+                self.send(&reply, from);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Sketch {
+        noise_scale: f32,
+        taps: u32,
+    }
+
+    fn controls() -> Vec<Control<Sketch>> {
+        vec![
+            Control::value("/test/noise_scale", |s| s.noise_scale, |s, v| s.noise_scale = v),
+            Control::trigger("/tap", |s| s.taps += 1),
+        ]
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let message = Message::new(
+            "/flow/noise_scale",
+            vec![
+                Arg::Float(60.0),
+                Arg::Int(-3),
+                Arg::String("four".to_string()),
+                Arg::Bool(true),
+                Arg::Double(0.5),
+                Arg::Long(1 << 40),
+            ],
+        );
+        let bytes = message.encode();
+        assert_eq!(bytes.len() % 4, 0);
+        assert_eq!(Message::decode(&bytes), Some(message));
+    }
+
+    #[test]
+    fn decodes_bytes_from_the_spec() {
+        // "/oscillator/4/frequency" ,f 440.0
+        let mut bytes = b"/oscillator/4/frequency\0,f\0\0".to_vec();
+        bytes.extend_from_slice(&[0x43, 0xdc, 0x00, 0x00]);
+
+        let message = Message::decode(&bytes).unwrap();
+        assert_eq!(message.address, "/oscillator/4/frequency");
+        assert_eq!(message.args, vec![Arg::Float(440.0)]);
+    }
+
+    #[test]
+    fn unpacks_bundles() {
+        let first = Message::new("/tap", vec![]).encode();
+        let second = Message::new("/clear", vec![Arg::Int(1)]).encode();
+
+        let mut bundle = BUNDLE_TAG.to_vec();
+        bundle.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        for element in [&first, &second] {
+            bundle.extend_from_slice(&(element.len() as u32).to_be_bytes());
+            bundle.extend_from_slice(element);
+        }
+
+        let messages = decode_packet(&bundle);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].address, "/clear");
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(Message::decode(b"hello"), None);
+        assert_eq!(Message::decode(b"/a\0\0,x\0\0"), None);
+        assert!(decode_packet(b"").is_empty());
+    }
+
+    #[test]
+    fn sets_and_queries_values() {
+        let controls = controls();
+        let mut sketch = Sketch::default();
+
+        let set = Message::new("/test/noise_scale", vec![Arg::Float(30.0)]);
+        assert!(dispatch(&controls, &mut sketch, &set).is_empty());
+        assert_eq!(sketch.noise_scale, 30.0);
+
+        for junk in [Arg::Float(f32::NAN), Arg::Double(f64::INFINITY), Arg::String("NaN".into())] {
+            dispatch(&controls, &mut sketch, &Message::new("/test/noise_scale", vec![junk]));
+        }
+        assert_eq!(sketch.noise_scale, 30.0);
+
+        let query = Message::new("/test/noise_scale", vec![]);
+        let replies = dispatch(&controls, &mut sketch, &query);
+        assert_eq!(replies, vec![Message::new("/test/noise_scale", vec![Arg::Float(30.0)])]);
+
+        let everything = dispatch(&controls, &mut sketch, &Message::new("/query", vec![]));
+        assert_eq!(everything.len(), 1);
+    }
+
+    #[test]
+    fn triggers_fire_on_press_only() {
+        let controls = controls();
+        let mut sketch = Sketch::default();
+
+        dispatch(&controls, &mut sketch, &Message::new("/tap", vec![]));
+        dispatch(&controls, &mut sketch, &Message::new("/tap", vec![Arg::Float(1.0)]));
+        dispatch(&controls, &mut sketch, &Message::new("/tap", vec![Arg::Float(0.0)]));
+        assert_eq!(sketch.taps, 2);
+    }
+
+    #[test]
+    fn serves_over_udp() {
+        let server = Server::bind(0).unwrap();
+        let port = server.local_addr().unwrap().port();
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        client
+            .set_read_timeout(Some(std::time::Duration::from_secs(1)))
+            .unwrap();
+
+        let controls = controls();
+        let mut sketch = Sketch::default();
+        let set = Message::new("/test/noise_scale", vec![Arg::Float(12.0)]);
+        let query = Message::new("/test/noise_scale", vec![]);
+        client.send_to(&set.encode(), (Ipv4Addr::LOCALHOST, port)).unwrap();
+        client.send_to(&query.encode(), (Ipv4Addr::LOCALHOST, port)).unwrap();
+
+        for _ in 0..100 {
+            server.update(&controls, &mut sketch);
+            if sketch.noise_scale == 12.0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        server.update(&controls, &mut sketch);
+
+        let mut buffer = [0; 256];
+        let length = client.recv(&mut buffer).unwrap();
+        let reply = Message::decode(&buffer[..length]).unwrap();
+        assert_eq!(reply.args, vec![Arg::Float(12.0)]);
+    }
+}
@@ -0,0 +1,105 @@
+// What every daily implements, so the launcher can run any of them in the same window and switch
+// between them live.
+//
+// Sketches are still written the nannou way, as a `Model` with free `model`/`update`/`view`
+// functions; their `Sketch` impl just points at them. The launcher owns the window and the OSC
+// server, so a sketch neither builds one nor binds the other.
+
+use nannou::prelude::*;
+use std::time::SystemTime;
+
+use crate::field::Generator;
+use crate::osc::Message;
+use crate::tap;
+
+pub trait Sketch {
+    /// Set up in the launcher's window, which is already `size()` big. `args` are whatever came
+    /// after the sketch's name on the command line.
+    fn new(app: &App, args: &[String]) -> Self
+    where
+        Self: Sized;
+
+    /// The window it was made for.
+    fn size() -> (u32, u32)
+    where
+        Self: Sized,
+    {
+        (720, 720)
+    }
+
+    fn update(&mut self, app: &App, update: Update);
+
+    /// Draw this frame into `draw`. The launcher renders it off screen so it can mix it with
+    /// another sketch, and it keeps what was drawn last frame unless the sketch sets a background.
+    fn view(&self, app: &App, draw: &Draw);
+
+    fn key_pressed(&mut self, _app: &App, _key: Key) {}
+
+    fn key_released(&mut self, _app: &App, _key: Key) {}
+
+    fn mouse_pressed(&mut self, _app: &App, _button: MouseButton) {}
+
+    /// Apply an OSC message to its parameters, returning any replies. Sketches with
+    /// `osc::Control`s hand it to `osc::dispatch`; the rest have nothing to set.
+    fn params(&mut self, _message: &Message) -> Vec<Message> {
+        Vec::new()
+    }
+
+    /// Its tap tempo, for sketches that keep one, so the next sketch can pick up the beat and a
+    /// transition can wait for the bar.
+    fn metro(&self) -> Option<&tap::Model> {
+        None
+    }
+
+    fn metro_mut(&mut self) -> Option<&mut tap::Model> {
+        None
+    }
+
+    /// The field its agents steer by, for sketches that have them, so it can be switched while
+    /// they run.
+    fn field_mut(&mut self) -> Option<&mut Generator> {
+        None
+    }
+}
+
+/// A sketch the launcher can start by name.
+#[derive(Clone, Copy)]
+pub struct Entry {
+    pub name: &'static str,
+    pub size: (u32, u32),
+    pub new: fn(&App, &[String]) -> Box<dyn Sketch>,
+}
+
+impl Entry {
+    pub fn of<S: Sketch + 'static>(name: &'static str) -> Entry {
+        Entry {
+            name,
+            size: S::size(),
+            new: boxed::<S>,
+        }
+    }
+}
+
+fn boxed<S: Sketch + 'static>(app: &App, args: &[String]) -> Box<dyn Sketch> {
+    Box::new(S::new(app, args))
+}
+
+/// `name`'s place in `entries`.
+pub fn find(entries: &[Entry], name: &str) -> Option<usize> {
+    entries.iter().position(|entry| entry.name == name)
+}
+
+/// The entry `step` along from `index`, wrapping around either end.
+pub fn step(entries: &[Entry], index: usize, step: isize) -> usize {
+    (index as isize + step).rem_euclid(entries.len() as isize) as usize
+}
+
+/// Somewhere to save a screenshot. Every sketch runs in the one binary, so it's stamped with the
+/// time rather than named after it, and one screenshot doesn't overwrite the last.
+pub fn screenshot_name(app: &App) -> String {
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!("{}-{}.png", app.exe_name().unwrap(), millis)
+}
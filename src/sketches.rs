@@ -0,0 +1,65 @@
+// Every daily the launcher knows, in the order it steps through them.
+
+use crate::sketch::Entry;
+
+pub mod circle_rainbow;
+pub mod controller;
+pub mod daily1;
+pub mod daily2;
+pub mod daily3;
+pub mod daily4;
+pub mod daily7;
+pub mod daily8;
+pub mod daily9;
+pub mod flow;
+pub mod move_ease;
+pub mod move_spring;
+pub mod tap_tempo_particle_flow;
+pub mod worms;
+
+/// What the launcher runs without a name.
+pub const DEFAULT: &str = "flow";
+
+pub fn all() -> Vec<Entry> {
+    vec![
+        Entry::of::<daily1::Model>("1"),
+        Entry::of::<daily2::Model>("2"),
+        Entry::of::<daily3::Model>("3"),
+        Entry::of::<daily4::Model>("4"),
+        Entry::of::<move_ease::Model>("5_move_ease"),
+        Entry::of::<move_spring::Model>("5_move_spring"),
+        Entry::of::<daily7::Model>("7"),
+        Entry::of::<daily8::Model>("8"),
+        Entry::of::<daily9::Model>("9"),
+        Entry::of::<tap_tempo_particle_flow::Model>("tap_tempo_particle_flow"),
+        Entry::of::<worms::Model>("worms"),
+        Entry::of::<flow::Model>("flow"),
+        Entry::of::<controller::Data>("controller"),
+        Entry::of::<circle_rainbow::Data>("circle_rainbow"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sketch::{find, step};
+
+    #[test]
+    fn names_are_unique() {
+        let entries = all();
+        for (i, entry) in entries.iter().enumerate() {
+            assert_eq!(find(&entries, entry.name), Some(i));
+        }
+        assert!(find(&entries, DEFAULT).is_some());
+        assert_eq!(find(&entries, "10"), None);
+    }
+
+    #[test]
+    fn steps_around_both_ends() {
+        let entries = all();
+        let last = entries.len() - 1;
+        assert_eq!(step(&entries, last, 1), 0);
+        assert_eq!(step(&entries, 0, -1), last);
+        assert_eq!(step(&entries, 2, 1), 3);
+    }
+}
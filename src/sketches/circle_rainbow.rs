@@ -1,8 +1,6 @@
 use nannou::prelude::*;
 
-fn main() {
-    nannou::app(data).update(update).run();
-}
+use crate::sketch::Sketch;
 
 pub struct Data {
     r: f32,
@@ -10,15 +8,25 @@ pub struct Data {
     b: f32,
 }
 
-fn data(app: &App) -> Data {
-    app.new_window()
-        .size(1920, 1080)
-        .view(draw)
-        .title("🔴")
-        .build()
-        .unwrap();
+impl Sketch for Data {
+    fn new(app: &App, args: &[String]) -> Data {
+        data(app, args)
+    }
+
+    fn size() -> (u32, u32) {
+        (1920, 1080)
+    }
+
+    fn update(&mut self, app: &App, frame_update: Update) {
+        update(app, self, frame_update);
+    }
 
+    fn view(&self, app: &App, draw: &Draw) {
+        view(app, self, draw);
+    }
+}
 
+fn data(_app: &App, _args: &[String]) -> Data {
     Data {
         r: 0.0,
         g: 0.0,
@@ -35,11 +43,9 @@ fn update(_app: &App, data: &mut Data, frame_update: Update) {
     data.b = ((t * PI * 1.0 * s).sin() + 1.0)/2.0;
 }
 
-fn draw(app: &App, data: &Data, frame: Frame) {
-    let draw = app.draw();
+fn view(_app: &App, data: &Data, draw: &Draw) {
     draw.background().rgb(0.0, 0.0, 0.0);
     draw.ellipse()
         .rgb(data.r, data.g, data.b);
-    draw.to_frame(app, &frame).unwrap();
 }
 
@@ -1,9 +1,7 @@
 use nannou::prelude::*;
-use gilrs::{Gilrs, Event, EventType};
 
-fn main() {
-    nannou::app(data).update(update).run();
-}
+use crate::sketch::Sketch;
+use gilrs::{Gilrs, Event, EventType};
 
 pub struct Data {
     r: f32,
@@ -11,19 +9,31 @@ pub struct Data {
     b: f32,
     x: f32,
     y: f32,
-    gilrs: Gilrs,
+    gilrs: Option<Gilrs>,
 }
 
-fn data(app: &App) -> Data {
-    let gilrs = Gilrs::new().unwrap();
-    app.new_window()
-        .size(1920, 1080)
-        .view(draw)
-        .title("🔴")
-        .build()
-        .unwrap();
+impl Sketch for Data {
+    fn new(app: &App, args: &[String]) -> Data {
+        data(app, args)
+    }
+
+    fn size() -> (u32, u32) {
+        (1920, 1080)
+    }
+
+    fn update(&mut self, app: &App, frame_update: Update) {
+        update(app, self, frame_update);
+    }
 
+    fn view(&self, app: &App, draw: &Draw) {
+        view(app, self, draw);
+    }
+}
 
+fn data(_app: &App, _args: &[String]) -> Data {
+    let gilrs = Gilrs::new()
+        .map_err(|e| println!("no gamepads: {}", e))
+        .ok();
     Data {
         r: 0.0,
         g: 0.0,
@@ -36,7 +46,7 @@ fn data(app: &App) -> Data {
 
 
 fn update(app: &App, data: &mut Data, frame_update: Update) {
-    while let Some(Event { id: _, event, time: _ }) = data.gilrs.next_event() {
+    while let Some(Event { id: _, event, time: _ }) = data.gilrs.as_mut().and_then(Gilrs::next_event) {
         match event {
             EventType::ButtonPressed(button, _) => {
                 println!("Button pressed: {:?}", button);
@@ -67,12 +77,10 @@ fn update(app: &App, data: &mut Data, frame_update: Update) {
     data.b = ((t * PI * 1.0 * s).sin() + 1.0)/2.0;
 }
 
-fn draw(app: &App, data: &Data, frame: Frame) {
-    let draw = app.draw();
+fn view(_app: &App, data: &Data, draw: &Draw) {
     draw.background().rgb(0.0, 0.0, 0.0);
     draw.ellipse()
         .x_y(data.x, data.y)
         .rgb(data.r, data.g, data.b);
-    draw.to_frame(app, &frame).unwrap();
 }
 
@@ -0,0 +1,160 @@
+// M_1_5_03
+//
+// Generative Gestaltung – Creative Coding im Web
+// ISBN: 978-3-87439-902-9, First Edition, Hermann Schmidt, Mainz, 2018
+// Benedikt Groß, Hartmut Bohnacker, Julia Laub, Claudius Lazzeroni
+// with contributions by Joey Lee and Niels Poldervaart
+// Copyright 2018
+//
+// http://www.generative-gestaltung.de
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+/**
+ * noise values (noise 3d) are used to animate a bunch of agents.
+ *
+ * KEYS
+ * 1-2                 : switch noise mode
+ * space               : new noise seed
+ * backspace           : clear screen
+ * s                   : save png
+ */
+use nannou::prelude::*;
+
+use crate::sketch::{screenshot_name, Sketch};
+
+use crate::agents::{drifting_colour, Angle, Steering, Swarm};
+use crate::field::{self, Generator};
+
+pub struct Model {
+    swarm: Swarm,
+    noise_scale: f64,
+    noise_strength: f64,
+    noise_z_velocity: f64,
+    overlay_alpha: f32,
+    agent_alpha: f32,
+    stroke_width: f32,
+    draw_mode: u8,
+    field: Generator,
+    base_speed: f32,
+    oscillator_freq_mult: f32,
+    oscillator_amp: f32,
+    start_time: std::time::Instant,
+    oscillator: f32,
+    oscillator_old: f32,
+    oscillator_above_0: bool,
+    oscillator_above_0_old: bool,
+}
+
+impl Sketch for Model {
+    fn new(app: &App, args: &[String]) -> Model {
+        model(app, args)
+    }
+
+    fn update(&mut self, app: &App, frame_update: Update) {
+        update(app, self, frame_update);
+    }
+
+    fn view(&self, app: &App, draw: &Draw) {
+        view(app, self, draw);
+    }
+
+    fn field_mut(&mut self) -> Option<&mut Generator> {
+        Some(&mut self.field)
+    }
+
+    fn key_released(&mut self, app: &App, key: Key) {
+        key_released(app, self, key);
+    }
+}
+
+fn model(app: &App, _args: &[String]) -> Model {
+    let noise_z_range = 0.4;
+    let agent_count = 4000;
+    let swarm = Swarm::new(agent_count, app.window_rect(), noise_z_range, 0.3..6.0);
+
+    let oscillator = 0.0;
+
+    Model {
+        swarm,
+        noise_scale: 100.0,
+        noise_strength: 3.0,
+        noise_z_velocity: 0.001,
+        overlay_alpha: 0.8,
+        agent_alpha: 1.0,
+        stroke_width: 0.7,
+        base_speed: 0.6,
+        oscillator_amp: 1.0,
+        oscillator_freq_mult: 0.5,
+        draw_mode: 1,
+        field: Generator::new(field::Kind::Perlin, 12),
+        oscillator,
+        oscillator_old: oscillator,
+        oscillator_above_0: true,
+        oscillator_above_0_old: true,
+        start_time: std::time::Instant::now(),
+    }
+}
+
+fn update(app: &App, model: &mut Model, _update: Update) {
+    let elapsed = model.start_time.elapsed();
+    let elapsed_secs = elapsed.as_secs_f32();
+    let z = elapsed.as_secs_f64() * 0.5;
+
+    model.oscillator_old = model.oscillator;
+    model.oscillator_above_0_old = model.oscillator_above_0;
+
+    model.oscillator = (elapsed_secs * std::f32::consts::PI * model.oscillator_freq_mult).sin()*model.oscillator_amp;
+    model.oscillator_above_0 = model.oscillator > 0.0;
+
+    if model.oscillator_above_0 && model.oscillator_above_0 != model.oscillator_above_0_old {
+        model.field.set_seed((random_f32() * 10000.0).floor() as u32);
+    }
+
+    let steering = Steering::new(model.noise_scale, model.noise_strength);
+    match model.draw_mode {
+        1 => model.swarm.steer(&model.field, &Steering { z: Some(z), ..steering }),
+        2 => model.swarm.steer(&model.field, &Steering { angle: Angle::Wrapped, ..steering }),
+        _ => (),
+    }
+    model.swarm.step(model.oscillator, model.noise_z_velocity, app.window_rect());
+}
+
+fn view(app: &App, model: &Model, draw: &Draw) {
+    if app.keys.down.contains(&Key::Back) {
+        draw.background().rgba(1.0, 1.0, 1.0, 1.0);
+    } else {
+        let alpha = model.oscillator.abs()*model.overlay_alpha;
+        draw.rect()
+            .wh(app.window_rect().wh())
+            .rgba(1.0, 1.0, 1.0, alpha);
+    }
+
+    let colour = drifting_colour(model.start_time.elapsed().as_secs_f32(), model.agent_alpha);
+    model.swarm.draw_trails(draw, colour, model.stroke_width);
+}
+
+fn key_released(app: &App, model: &mut Model, key: Key) {
+    match key {
+        Key::Key1 => model.draw_mode = 1,
+        Key::Key2 => model.draw_mode = 2,
+        Key::Left => model.base_speed -= 0.1,
+        Key::Right => model.base_speed += 0.1,
+        Key::Space => {
+            model.field.set_seed((random_f32() * 10000.0).floor() as u32);
+        }
+        Key::S => {
+            let name = screenshot_name(app);
+            println!("saving screenshot: {}", name);
+            app.main_window()
+                .capture_frame(name);
+        }
+        _other_key => {}
+    }
+}
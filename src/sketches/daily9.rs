@@ -0,0 +1,223 @@
+use nannou::prelude::*;
+
+use crate::sketch::{screenshot_name, Sketch};
+
+use crate::agents::{drifting_colour, Heading, Steering, Swarm};
+use crate::field::{self, Generator};
+
+use crate::tap::{update as update_metro, view as view_metro, Model as Metro};
+
+pub struct Model {
+    swarm: Swarm,
+    noise_scale: f64,
+    noise_strength: f64,
+    noise_z_velocity: f64,
+    agent_alpha: f32,
+    draw_mode: DrawMode,
+    field: Generator,
+    oscillator_freq_mult: f32,
+    oscillator_amp: f32,
+    start_time: std::time::Instant,
+    oscillator: f32,
+    oscillator_old: f32,
+    oscillator_above_0: bool,
+    oscillator_above_0_old: bool,
+    metro: Metro,
+}
+
+impl Sketch for Model {
+    fn new(app: &App, args: &[String]) -> Model {
+        model(app, args)
+    }
+
+    fn size() -> (u32, u32) {
+        (1920, 1080)
+    }
+
+    fn update(&mut self, app: &App, frame_update: Update) {
+        update(app, self, frame_update);
+    }
+
+    fn view(&self, app: &App, draw: &Draw) {
+        view(app, self, draw);
+    }
+
+    fn field_mut(&mut self) -> Option<&mut Generator> {
+        Some(&mut self.field)
+    }
+
+    fn key_pressed(&mut self, app: &App, key: Key) {
+        key_pressed(app, self, key);
+    }
+
+    fn key_released(&mut self, app: &App, key: Key) {
+        key_released(app, self, key);
+    }
+
+    fn mouse_pressed(&mut self, app: &App, button: MouseButton) {
+        mouse_pressed(app, self, button);
+    }
+
+    fn metro(&self) -> Option<&Metro> {
+        Some(&self.metro)
+    }
+
+    fn metro_mut(&mut self) -> Option<&mut Metro> {
+        Some(&mut self.metro)
+    }
+}
+
+fn model(app: &App, _args: &[String]) -> Model {
+    let noise_z_range = 0.4;
+    let agent_count = 8192;
+    let swarm = Swarm::new(agent_count, app.window_rect(), noise_z_range, 0.3..6.0)
+        .with_heading(Heading::Up);
+
+    let oscillator = 0.0;
+
+    Model {
+        swarm,
+        noise_scale: 30.0,
+        noise_strength: 1.0,
+        noise_z_velocity: 0.01,
+        agent_alpha: 0.125,
+        oscillator_amp: 2.3,
+        oscillator_freq_mult: 0.1,
+        draw_mode: DrawMode::Trails,
+        field: Generator::new(field::Kind::Perlin, 12),
+        oscillator,
+        oscillator_old: oscillator,
+        oscillator_above_0: true,
+        oscillator_above_0_old: true,
+        start_time: std::time::Instant::now(),
+        metro: Metro::default(),
+    }
+}
+
+
+enum DrawMode {
+    NoTrails,
+    Trails,
+}
+
+fn update(app: &App, model: &mut Model, frame_update: Update) {
+    let elapsed = if !model.metro.taps.is_empty() {
+        model.metro.taps[model.metro.taps.len()-1].elapsed()
+    } else {
+        model.start_time.elapsed()
+    };
+    let elapsed_secs = elapsed.as_secs_f32();
+    let z = elapsed.as_secs_f64() * 0.5;
+
+    update_metro(app, &mut model.metro, frame_update);
+
+    model.oscillator_old = model.oscillator;
+    model.oscillator_above_0_old = model.oscillator_above_0;
+
+    // holds still until there's a tempo
+    let frequency = model.metro.bpm.unwrap_or(0.0) as f32 / 60.0;
+    model.oscillator_freq_mult = frequency;
+
+    model.oscillator = pow(
+        (elapsed_secs * std::f32::consts::PI * model.oscillator_freq_mult).sin(),
+        1,
+    ) * model.oscillator_amp;
+
+    model.oscillator_above_0 = model.oscillator > 0.0;
+
+    if model.oscillator_above_0 && model.oscillator_above_0 != model.oscillator_above_0_old {
+        model.field.set_seed((random_f32() * 10000.0).floor() as u32);
+    }
+
+    let bounds = app.window_rect();
+    let steering = Steering {
+        z: Some(z),
+        scroll: elapsed_secs as f64,
+        ..Steering::new(model.noise_scale, model.noise_strength)
+    };
+    model.swarm.steer(&model.field, &steering);
+    model.swarm.step(model.oscillator, model.noise_z_velocity, bounds);
+}
+
+fn view(app: &App, model: &Model, draw: &Draw) {
+    let elapsed = model.start_time.elapsed();
+    let elapsed_secs = elapsed.as_secs_f32();
+    let r = (elapsed_secs * 0.03 * std::f32::consts::PI + 3.0)
+        .sin()
+        .abs()
+        * 0.1;
+    let g = (elapsed_secs * 0.005 * std::f32::consts::PI + 6.0)
+        .sin()
+        .abs()
+        * 0.05;
+    let b = (elapsed_secs * 0.05 * std::f32::consts::PI).sin().abs() * 0.3;
+
+    let alpha = match model.draw_mode {
+        DrawMode::NoTrails => 1.0,
+        DrawMode::Trails => model.oscillator.abs() * 0.3,
+    };
+
+    draw.rect()
+        .wh(app.window_rect().wh())
+        .rgba(r, g, b, alpha);
+
+    let colour = drifting_colour(elapsed_secs, model.agent_alpha);
+    match model.draw_mode {
+        DrawMode::NoTrails => {
+            let mut colour = colour;
+            colour.red = 1.0;
+            model.swarm.draw_trails(draw, colour, 0.5)
+        }
+        DrawMode::Trails => model.swarm.draw_trails(draw, colour, 0.5),
+    }
+
+    view_metro(app, &model.metro, draw);
+}
+
+fn key_released(app: &App, _model: &mut Model, key: Key) {
+    match key {
+        Key::S => {
+            let name = screenshot_name(app);
+            println!("saving screenshot: {}", name);
+            app.main_window().capture_frame(name);
+        }
+        _other_key => {}
+    }
+}
+
+// keeps the beat where it is, like the nudge keys in the later dailies
+fn scale_bpm(model: &mut Model, factor: f64) {
+    if let Some(bpm) = model.metro.bpm {
+        model.metro.nudge_bpm(bpm * factor - bpm);
+    }
+}
+
+fn tap(model: &mut Model) {
+    model.metro.tap()
+}
+
+pub fn mouse_pressed(_app: &App, model: &mut Model, mouse_button: MouseButton) {
+    match mouse_button {
+        MouseButton::Left => tap(model),
+        MouseButton::Right => model.metro.clear(),
+        _ => {}
+    }
+}
+
+pub fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    match key {
+
+        // home row fingers
+        Key::F => model.draw_mode = DrawMode::Trails,
+        Key::D => model.draw_mode = DrawMode::NoTrails,
+        Key::J => scale_bpm(model, 1.0/4.0),
+        Key::K => scale_bpm(model, 1.0/2.0),
+        Key::L => scale_bpm(model, 2.0),
+        Key::Semicolon => scale_bpm(model, 4.0),
+        Key::Space => tap(model),
+        Key::Back => model.metro.clear(),
+        Key::Delete => model.metro.clear(),
+        Key::Q => app.quit(),
+        _ => {}
+    }
+}
@@ -0,0 +1,186 @@
+use nannou::prelude::*;
+
+use crate::sketch::{screenshot_name, Sketch};
+use gilrs::{Gilrs, Event, EventType};
+
+use crate::agents::{drifting_colour, Heading, Margin, Swarm};
+use crate::automation::Automation;
+use crate::osc::{self, Control, Message};
+
+const DEADZONE: f32 = 0.125;
+// draw with `draw_lines` instead
+const THIN_LINES: bool = false;
+
+pub struct Model {
+    swarm: Swarm,
+    agent_alpha: f32,
+    start_time: std::time::Instant,
+    oscillator: f32,
+    oscillator_old: f32,
+    // keyframed parameters, which take over from the controls for anything they have a lane for
+    automation: Option<Automation>,
+    gilrs: Option<Gilrs>,
+    stick_y: f32,
+}
+
+impl Sketch for Model {
+    fn new(app: &App, args: &[String]) -> Model {
+        model(app, args)
+    }
+
+    fn size() -> (u32, u32) {
+        (1920, 1080)
+    }
+
+    fn update(&mut self, app: &App, frame_update: Update) {
+        update(app, self, frame_update);
+    }
+
+    fn view(&self, app: &App, draw: &Draw) {
+        view(app, self, draw);
+    }
+
+    fn key_pressed(&mut self, app: &App, key: Key) {
+        key_pressed(app, self, key);
+    }
+
+    fn key_released(&mut self, app: &App, key: Key) {
+        key_released(app, self, key);
+    }
+
+    fn params(&mut self, message: &Message) -> Vec<Message> {
+        osc::dispatch(&osc_controls(), self, message)
+    }
+}
+
+
+fn model(app: &App, args: &[String]) -> Model {
+    let gilrs = Gilrs::new()
+        .map_err(|e| println!("no gamepads: {}", e))
+        .ok();
+
+    let noise_z_range = 0.4;
+    let agent_count = 30;
+    let swarm = Swarm::new(agent_count, app.window_rect(), noise_z_range, 30.0..60.0)
+        .with_heading(Heading::Up)
+        .with_margin(Margin::StepSize)
+        .with_speed(6.0..10.0)
+        .with_random_angles();
+
+    let oscillator = 0.0;
+
+    let automation = args.first().and_then(|path| {
+        Automation::load(path)
+            .map_err(|e| println!("couldn't load automation {}: {}", path, e))
+            .ok()
+    });
+
+    Model {
+        swarm,
+        agent_alpha: 1.0,
+        oscillator,
+        oscillator_old: oscillator,
+        start_time: std::time::Instant::now(),
+        automation,
+        gilrs,
+        stick_y: 0.0,
+    }
+}
+
+fn osc_controls() -> Vec<Control<Model>> {
+    vec![
+        Control::value(
+            "/flow/agent_alpha",
+            |m| m.agent_alpha,
+            |m, v| m.agent_alpha = v.clamp(0.0, 1.0),
+        ),
+    ]
+}
+
+fn update(app: &App, model: &mut Model, _frame_update: Update) {
+    let elapsed = model.start_time.elapsed();
+
+    let bounds = app.window_rect();
+
+    // there's no tempo here, so automation in beats plays at 120bpm
+    if let Some(automation) = model.automation.take() {
+        let seconds = elapsed.as_secs_f64();
+        automation.apply(&osc_controls(), model, automation.position(seconds, seconds * 2.0));
+        model.automation = Some(automation);
+    }
+
+    model.oscillator_old = model.oscillator;
+    model.oscillator = model.stick_y * 2.0;
+
+    while let Some(Event { id: _, event, time: _ }) = model.gilrs.as_mut().and_then(Gilrs::next_event) {
+        if let EventType::AxisChanged(gilrs::Axis::RightStickY, value, _) = event {
+            if value.abs() > DEADZONE {
+                model.stick_y = value;
+            } else {
+                model.stick_y = 0.0;
+            }
+        }
+    }
+
+    // model.oscillator = model.stick_y;
+    model.oscillator += 0.1;
+
+    // the agents go where the stick sends them, without a field to turn them
+    model.swarm.step(model.oscillator, 0.0, bounds);
+}
+
+fn view(app: &App, model: &Model, draw: &Draw) {
+    let elapsed = model.start_time.elapsed();
+    let elapsed_secs = elapsed.as_secs_f32();
+    let r = (elapsed_secs * 0.03 * std::f32::consts::PI + 3.0)
+        .sin()
+        .abs()
+        * 0.1;
+    let g = (elapsed_secs * 0.005 * std::f32::consts::PI + 6.0)
+        .sin()
+        .abs()
+        * 0.05;
+    let b = (elapsed_secs * 0.05 * std::f32::consts::PI).sin().abs() * 0.3;
+
+    let alpha = 0.03;
+
+    draw.rect()
+        .wh(app.window_rect().wh())
+        .rgba(r, g, b, alpha);
+
+    if THIN_LINES {
+        draw_lines(model, draw, model.agent_alpha);
+    } else {
+        let colour = drifting_colour(elapsed_secs, model.agent_alpha);
+        model.swarm.draw_trails(draw, colour, 0.5);
+        model.swarm.draw_dots(draw, colour, 1.0);
+    }
+
+}
+
+// thin trails with the red turned all the way up, instead of the trails and dots
+fn draw_lines(model: &Model, draw: &Draw, agent_alpha: f32) {
+    let elapsed_secs = model.start_time.elapsed().as_secs_f32();
+    let mut colour = drifting_colour(elapsed_secs, agent_alpha);
+    colour.red = 1.0;
+    model.swarm.draw_trails(draw, colour, 1.0 / 15.0);
+}
+
+fn key_released(app: &App, _model: &mut Model, key: Key) {
+    match key {
+        Key::S => {
+            let name = screenshot_name(app);
+            println!("saving screenshot: {}", name);
+            app.main_window().capture_frame(name);
+        }
+        _other_key => {}
+    }
+}
+
+pub fn key_pressed(app: &App, _model: &mut Model, key: Key) {
+    match key {
+        Key::Escape =>  app.quit(),
+        Key::Q => app.quit(),
+        _ => {}
+    }
+}
@@ -0,0 +1,71 @@
+use nannou::prelude::*;
+
+use crate::sketch::Sketch;
+
+use crate::tween::ease::{Curve, Ease};
+use crate::tween::Tween;
+
+// seconds to get to wherever was clicked
+const DURATION: f64 = 0.8;
+
+pub struct Model {
+    position: Vec2,
+    tween: Tween<Vec2>,
+    // when the tween started, in `app.time`
+    started: f64,
+    curve: usize,
+}
+
+impl Sketch for Model {
+    fn new(app: &App, args: &[String]) -> Model {
+        model(app, args)
+    }
+
+    fn update(&mut self, app: &App, frame_update: Update) {
+        update(app, self, frame_update);
+    }
+
+    fn view(&self, app: &App, draw: &Draw) {
+        view(app, self, draw);
+    }
+
+    fn key_pressed(&mut self, app: &App, key: Key) {
+        key_pressed(app, self, key);
+    }
+
+    fn mouse_pressed(&mut self, app: &App, button: MouseButton) {
+        click(app, self, button);
+    }
+}
+
+fn model(_app: &App, _args: &[String]) -> Model {
+    Model {
+        position: Vec2::ZERO,
+        tween: Tween::new(Vec2::ZERO, Vec2::ZERO, DURATION),
+        started: 0.0,
+        curve: 0,
+    }
+}
+
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.position = model.tween.value_at(app.time as f64 - model.started);
+}
+
+fn view(_app: &App, model: &Model, draw: &Draw) {
+    draw.background().color(PLUM);
+    draw.ellipse().color(STEELBLUE).xy(model.position);
+}
+
+fn click(app: &App, model: &mut Model, _mouse_button: MouseButton) {
+    // from wherever it is now, even if it hadn't got where it was going
+    let ease = Ease::Out(Curve::ALL[model.curve]);
+    model.tween = Tween::new(model.position, app.mouse.position(), DURATION).with_ease(ease);
+    model.started = app.time as f64;
+}
+
+fn key_pressed(_app: &App, model: &mut Model, key: Key) {
+    if key == Key::Space {
+        model.curve = (model.curve + 1) % Curve::ALL.len();
+        println!("{:?}", Curve::ALL[model.curve]);
+    }
+}
@@ -0,0 +1,42 @@
+use nannou::prelude::*;
+
+use crate::sketch::Sketch;
+
+use crate::tap::spring::Spring;
+
+pub struct Model {
+    position: Spring<Vec2>,
+}
+
+impl Sketch for Model {
+    fn new(app: &App, args: &[String]) -> Model {
+        model(app, args)
+    }
+
+    fn update(&mut self, app: &App, frame_update: Update) {
+        update(app, self, frame_update);
+    }
+
+    fn view(&self, app: &App, draw: &Draw) {
+        view(app, self, draw);
+    }
+}
+
+fn model(_app: &App, _args: &[String]) -> Model {
+    Model {
+        // a third of a second to catch up, with plenty of bounce
+        position: Spring::with_bounce(0.33, 0.85, Vec2::ZERO, Vec2::ZERO),
+    }
+}
+
+fn update(app: &App, model: &mut Model, update: Update) {
+    if app.mouse.buttons.left().is_down() {
+        model.position.retarget(app.mouse.position());
+    }
+    model.position.update(update.since_last.as_secs_f32());
+}
+
+fn view(_app: &App, model: &Model, draw: &Draw) {
+    draw.background().color(PLUM);
+    draw.ellipse().color(STEELBLUE).xy(model.position.value);
+}
@@ -0,0 +1,731 @@
+use nannou::prelude::*;
+
+use crate::sketch::{screenshot_name, Sketch};
+use gilrs::{Gilrs, Event, EventType, Button};
+
+use crate::agents::{drifting_colour, Heading, Steering, Swarm};
+use crate::field::{self, Generator};
+use crate::automation::Automation;
+use crate::beats::BeatGrid;
+use crate::midi::input::ClockInput;
+use crate::midi::output::ClockOutput;
+use crate::osc::{self, Control, Message};
+use crate::sync::{State as SyncState, SyncGroup};
+use crate::tap::clock::Clock;
+use crate::tap::event::Event as MetroEvent;
+use crate::tap::groove::Subdivision;
+use crate::tap::meter::Meter;
+use crate::tap::unit::TapUnit;
+use crate::tap::{update as update_metro, view as view_metro, Model as Metro};
+use crate::tween::ease::{Curve, Ease};
+use crate::tween::timeline::Timeline;
+use crate::tween::Tween;
+use std::time::Instant;
+
+const DEADZONE: f32 = 0.125;
+// fine tempo adjustment per press
+const BPM_NUDGE: f64 = 0.1;
+// how much faster or slower the beat runs while a bend is held
+const BEND: f64 = 0.05;
+// what the swing key steps through, from straight to a full triplet shuffle
+const SWINGS: [f64; 4] = [50.0, 54.0, 58.0, 66.0];
+// what the meter key steps through
+const METERS: [Meter; 3] = [Meter::FOUR_FOUR, Meter::THREE_FOUR, Meter::SEVEN_FOUR];
+
+pub struct Model {
+    swarm: Swarm,
+    noise_scale: f64,
+    noise_strength: f64,
+    noise_z_velocity: f64,
+    agent_alpha: f32,
+    draw_mode: DrawMode,
+    field: Generator,
+    start_time: std::time::Instant,
+    oscillator: f32,
+    oscillator_old: f32,
+    oscillator_above_0: bool,
+    oscillator_above_0_old: bool,
+    oscillator_amp: f32,
+    frequency_multiplier: f32,
+    // what the multiplier goes back to when no multiplier key or button is held
+    base_frequency_multiplier: f32,
+    // a bend held over OSC, -1 to 1
+    osc_bend: f64,
+    // the bar the picture was in last frame
+    visual_bar: u64,
+    // a swell in the noise, played in beats
+    sweep: Option<Timeline<Model>>,
+    // the visual clock the sweep was last played at
+    sweep_beats: f64,
+    metro: Metro,
+    grid: Option<BeatGrid>,
+    // keyframed parameters, which take over from the controls for anything they have a lane for
+    automation: Option<Automation>,
+    // when the automation started playing, on the wall clock and the beat clock
+    automation_start: (Instant, f64),
+    midi_clock: Option<ClockInput>,
+    midi_clock_out: Option<ClockOutput>,
+    sync: Option<SyncGroup>,
+    gilrs: Option<Gilrs>,
+    stick_y: f32,
+}
+
+impl Sketch for Model {
+    fn new(app: &App, args: &[String]) -> Model {
+        model(app, args)
+    }
+
+    fn size() -> (u32, u32) {
+        (1920, 1080)
+    }
+
+    fn update(&mut self, app: &App, frame_update: Update) {
+        update(app, self, frame_update);
+    }
+
+    fn view(&self, app: &App, draw: &Draw) {
+        view(app, self, draw);
+    }
+
+    fn field_mut(&mut self) -> Option<&mut Generator> {
+        Some(&mut self.field)
+    }
+
+    fn key_pressed(&mut self, app: &App, key: Key) {
+        key_pressed(app, self, key);
+    }
+
+    fn key_released(&mut self, app: &App, key: Key) {
+        key_released(app, self, key);
+    }
+
+    fn mouse_pressed(&mut self, app: &App, button: MouseButton) {
+        mouse_pressed(app, self, button);
+    }
+
+    fn metro(&self) -> Option<&Metro> {
+        Some(&self.metro)
+    }
+
+    fn metro_mut(&mut self) -> Option<&mut Metro> {
+        Some(&mut self.metro)
+    }
+
+    fn params(&mut self, message: &Message) -> Vec<Message> {
+        osc::dispatch(&osc_controls(), self, message)
+    }
+}
+
+
+fn model(app: &App, args: &[String]) -> Model {
+    let gilrs = Gilrs::new()
+        .map_err(|e| println!("no gamepads: {}", e))
+        .ok();
+
+    let noise_z_range = 0.4;
+    let agent_count = 2048;
+    let swarm = Swarm::new(agent_count, app.window_rect(), noise_z_range, 0.3..6.0)
+        .with_heading(Heading::Up);
+
+    let oscillator = 0.0;
+
+    // a grid from `beat_grid`, to start along with its track by pressing return
+    let grid = args.first().and_then(|path| {
+        BeatGrid::load(path)
+            .map_err(|e| println!("couldn't load beat grid {}: {}", path, e))
+            .ok()
+    });
+    // and automation, played from launch and from the top again along with the track
+    let automation = args.get(1).and_then(|path| {
+        Automation::load(path)
+            .map_err(|e| println!("couldn't load automation {}: {}", path, e))
+            .ok()
+    });
+
+    // a drum machine or DAW can drive the tempo by sending MIDI clock to this port
+    let midi_clock = ClockInput::virtual_port("tap_tempo_particle_flow clock")
+        .map_err(|e| println!("no MIDI clock input: {}", e))
+        .ok();
+    // and anything listening on this one follows the tapped tempo
+    let midi_clock_out = ClockOutput::virtual_port("tap_tempo_particle_flow clock out")
+        .map_err(|e| println!("no MIDI clock output: {}", e))
+        .ok();
+    // other machines running this sketch on the network pulse and reseed along with it
+    let sync = SyncGroup::join()
+        .map_err(|e| println!("no network sync: {}", e))
+        .ok();
+
+    Model {
+        swarm,
+        noise_scale: 60.0,
+        noise_strength: 1.0,
+        noise_z_velocity: 0.01,
+        agent_alpha: 0.125,
+        draw_mode: DrawMode::Trails,
+        field: Generator::new(field::Kind::Perlin, 12),
+        oscillator,
+        oscillator_old: oscillator,
+        oscillator_above_0: true,
+        oscillator_amp: 2.3,
+        oscillator_above_0_old: true,
+        frequency_multiplier: 1.0,
+        base_frequency_multiplier: 1.0,
+        osc_bend: 0.0,
+        visual_bar: 0,
+        sweep: None,
+        sweep_beats: 0.0,
+        start_time: std::time::Instant::now(),
+        metro: Metro::default(),
+        grid,
+        automation,
+        automation_start: (Instant::now(), 0.0),
+        midi_clock,
+        midi_clock_out,
+        sync,
+        gilrs,
+        stick_y: 0.0,
+    }
+}
+
+
+fn osc_controls() -> Vec<Control<Model>> {
+    vec![
+        Control::value(
+            "/tap_tempo_particle_flow/noise_scale",
+            |m| m.noise_scale as f32,
+            |m, v| m.noise_scale = v.clamp(1.0, 10_000.0) as f64,
+        ),
+        Control::value(
+            "/tap_tempo_particle_flow/noise_strength",
+            |m| m.noise_strength as f32,
+            |m, v| m.noise_strength = v.clamp(0.0, 100.0) as f64,
+        ),
+        Control::value(
+            "/tap_tempo_particle_flow/noise_z_velocity",
+            |m| m.noise_z_velocity as f32,
+            |m, v| m.noise_z_velocity = v.clamp(-1.0, 1.0) as f64,
+        ),
+        Control::value(
+            "/tap_tempo_particle_flow/agent_alpha",
+            |m| m.agent_alpha,
+            |m, v| m.agent_alpha = v.clamp(0.0, 1.0),
+        ),
+        Control::value(
+            "/tap_tempo_particle_flow/oscillator_amp",
+            |m| m.oscillator_amp,
+            |m, v| m.oscillator_amp = v.clamp(0.0, 10.0),
+        ),
+        Control::value(
+            "/tap_tempo_particle_flow/frequency_multiplier",
+            |m| m.frequency_multiplier,
+            |m, v| {
+                let v = v.clamp(1.0 / 16.0, 16.0);
+                m.base_frequency_multiplier = v;
+                m.frequency_multiplier = v;
+            },
+        ),
+        // 0 for no trails, 1 for trails, so a toggle button maps straight onto it
+        Control::value(
+            "/tap_tempo_particle_flow/draw_mode",
+            |m| match m.draw_mode {
+                DrawMode::NoTrails => 0.0,
+                DrawMode::Trails => 1.0,
+            },
+            |m, v| {
+                m.draw_mode = if v >= 0.5 { DrawMode::Trails } else { DrawMode::NoTrails };
+            },
+        ),
+        // which of `field::Kind::ALL` the agents steer by, for a radio button
+        Control::value(
+            "/tap_tempo_particle_flow/field",
+            |m| m.field.kind().index() as f32,
+            |m, v| m.field.set_kind(field::Kind::nth(v as usize)),
+        ),
+        Control::trigger("/tap", |m| tap(m, m.metro.clock.now())),
+        Control::trigger("/clear", clear),
+        Control::trigger("/one", |m| m.metro.set_downbeat()),
+        Control::trigger("/resync", |m| {
+            m.metro.resync();
+            take_lead(m);
+        }),
+        Control::value(
+            "/ramp_beats",
+            |m| m.metro.ramp_beats as f32,
+            |m, v| m.metro.ramp_beats = v.clamp(0.0, 64.0) as f64,
+        ),
+        // an index into `TapUnit::ALL`, from half notes down to eighth note triplets
+        Control::value(
+            "/tap_unit",
+            |m| TapUnit::ALL.iter().position(|u| *u == m.metro.tap_unit).unwrap_or(0) as f32,
+            |m, v| {
+                let index = (v.round().max(0.0) as usize).min(TapUnit::ALL.len() - 1);
+                m.metro.tap_unit = TapUnit::ALL[index];
+            },
+        ),
+        Control::trigger("/accept_tempo", accept_tempo_suggestion),
+        // 50 is straight
+        Control::value(
+            "/swing",
+            |m| m.metro.groove.swing as f32,
+            |m, v| m.metro.groove.swing = v.clamp(50.0, 75.0) as f64,
+        ),
+        // 0 swings eighths, 1 swings sixteenths
+        Control::value(
+            "/swing_sixteenths",
+            |m| (m.metro.groove.subdivision == Subdivision::Sixteenth) as u8 as f32,
+            |m, v| {
+                m.metro.groove.subdivision = if v >= 0.5 {
+                    Subdivision::Sixteenth
+                } else {
+                    Subdivision::Eighth
+                };
+            },
+        ),
+        Control::trigger("/sweep", start_sweep),
+        // starts calibrating, or finishes if it already is
+        Control::trigger("/calibrate", toggle_calibration),
+        // how far ahead of the beat the picture runs, in milliseconds
+        Control::value(
+            "/latency",
+            |m| (m.metro.latency * 1000.0) as f32,
+            |m, v| m.metro.latency = v.clamp(-1000.0, 1000.0) as f64 / 1000.0,
+        ),
+        Control::trigger("/nudge_up", |m| nudge_bpm(m, BPM_NUDGE)),
+        Control::trigger("/nudge_down", |m| nudge_bpm(m, -BPM_NUDGE)),
+        // -1 to 1, held like the bend buttons
+        Control::value(
+            "/bend",
+            |m| m.osc_bend as f32,
+            |m, v| m.osc_bend = v.clamp(-1.0, 1.0) as f64,
+        ),
+        Control::value(
+            "/beats_per_bar",
+            |m| m.metro.meter().beats_per_bar as f32,
+            |m, v| {
+                let meter = Meter::new(v.round().clamp(1.0, 32.0) as u64);
+                m.metro.set_meter(meter);
+            },
+        ),
+    ]
+}
+
+enum DrawMode {
+    NoTrails,
+    Trails,
+}
+
+fn update(app: &App, model: &mut Model, frame_update: Update) {
+    let elapsed = model.start_time.elapsed();
+    let elapsed_secs = elapsed.as_secs_f32();
+    let z = elapsed.as_secs_f64() * 0.5;
+
+    let bounds = app.window_rect();
+
+    while let Some(Event { id: _, event, time }) = model.gilrs.as_mut().and_then(Gilrs::next_event) {
+        match event {
+            EventType::AxisChanged(gilrs::Axis::RightStickY, value, _) => {
+                if value.abs() > DEADZONE {
+                    model.stick_y = value;
+                } else {
+                    model.stick_y = 0.0;
+                }
+            }
+
+            EventType::ButtonPressed(button, _) => {
+                match button {
+                    Button::South => {
+                        let time = model.metro.event_instant(time);
+                        tap(model, time)
+                    },
+                    Button::East => { clear(model) },
+                    Button::North => {
+                        let time = model.metro.event_instant(time);
+                        model.metro.set_downbeat_at(time)
+                    },
+                    Button::West => {
+                        let time = model.metro.event_instant(time);
+                        model.metro.resync_at(time);
+                        take_lead(model);
+                    },
+                    Button::Select => accept_tempo_suggestion(model),
+                    Button::LeftTrigger => nudge_bpm(model, -BPM_NUDGE),
+                    Button::RightTrigger => nudge_bpm(model, BPM_NUDGE),
+
+                    Button::DPadUp => { model.frequency_multiplier = 4.0 },
+                    Button::DPadRight => { model.frequency_multiplier = 2.0 },
+                    Button::DPadDown => { model.frequency_multiplier = 1.0/4.0 },
+                    Button::DPadLeft => { model.frequency_multiplier = 1.0/2.0 },
+
+                    _ => {
+                    },
+                }
+            },
+
+            _ => {}
+        }
+    }
+
+    // bends last as long as they're held
+    let mut bend = model.osc_bend;
+    for (_id, gamepad) in model.gilrs.iter().flat_map(Gilrs::gamepads) {
+        if gamepad.is_pressed(Button::LeftTrigger2) {
+            bend -= 1.0;
+        }
+        if gamepad.is_pressed(Button::RightTrigger2) {
+            bend += 1.0;
+        }
+    }
+    if app.keys.down.contains(&Key::Left) {
+        bend -= 1.0;
+    }
+    if app.keys.down.contains(&Key::Right) {
+        bend += 1.0;
+    }
+    model.metro.set_bend(bend.clamp(-1.0, 1.0) * BEND);
+    if bend != 0.0 {
+        take_lead(model);
+    }
+
+    // gamepad taps are in before the metronome checks for a timeout
+    update_metro(app, &mut model.metro, frame_update);
+    if let Some(midi_clock) = &mut model.midi_clock {
+        midi_clock.update(&mut model.metro);
+        if midi_clock.is_running() {
+            take_lead(model);
+        }
+    }
+
+    let mut following = false;
+    if let Some(sync) = &mut model.sync {
+        let state = SyncState {
+            bpm: model.metro.bpm,
+            beats: model.metro.beats(),
+            noise_seed: model.field.seed(),
+            field_kind: model.field.kind(),
+        };
+        if let Some(leader) = sync.update(state) {
+            following = true;
+            match leader.bpm {
+                Some(bpm) => model.metro.sync(bpm, leader.beats),
+                None if model.metro.bpm.is_some() => model.metro.clear(),
+                None => {}
+            }
+            model.field.set_kind(leader.field_kind);
+            model.field.set_seed(leader.noise_seed);
+        }
+    }
+
+    if let Some(midi_clock_out) = &model.midi_clock_out {
+        midi_clock_out.update(&model.metro);
+    }
+
+    if let Some(automation) = model.automation.take() {
+        let (start, start_beats) = model.automation_start;
+        let position = automation.position(
+            start.elapsed().as_secs_f64(),
+            model.metro.visual_beats() - start_beats,
+        );
+        automation.apply(&osc_controls(), model, position);
+        model.automation = Some(automation);
+    }
+
+    model.oscillator_old = model.oscillator;
+    model.oscillator_above_0_old = model.oscillator_above_0;
+
+    if model.metro.bpm.is_some() {
+        // swung, so the pulse pushes and pulls with the groove
+        let beats = model.metro.grooved_beats() as f32;
+        model.oscillator = pow(
+            (beats * std::f32::consts::PI * model.frequency_multiplier).sin(),
+            3,
+        ) * model.oscillator_amp;
+    } else {
+        model.oscillator = model.stick_y * 2.0;
+    }
+
+    // model.oscillator = model.stick_y;
+
+    model.oscillator_above_0 = model.oscillator > 0.0;
+
+    // a new field every bar once there's a tempo, or on every upswing of the stick without one.
+    // followers take their seed from the leader
+    let mut tempo_gone = false;
+    for event in model.metro.drain_events() {
+        tempo_gone |= matches!(event, MetroEvent::TempoChanged { bpm: None });
+        log_metro_event(&event);
+    }
+    update_sweep(model, tempo_gone);
+    // bars as the picture sees them, so the new field lands with the kick rather than after it
+    let visual_bar = model.metro.visual_bar();
+    let downbeat = visual_bar != model.visual_bar;
+    model.visual_bar = visual_bar;
+    let reseed = if model.metro.bpm.is_some() {
+        downbeat
+    } else {
+        model.oscillator_above_0 && model.oscillator_above_0 != model.oscillator_above_0_old
+    };
+    if reseed && !following {
+        model.field.set_seed((random_f32() * 10000.0).floor() as u32);
+    }
+
+    let steering = Steering {
+        z: Some(z),
+        scroll: elapsed_secs as f64,
+        ..Steering::new(model.noise_scale, model.noise_strength)
+    };
+    model.swarm.steer(&model.field, &steering);
+    model.swarm.step(model.oscillator, model.noise_z_velocity, bounds);
+
+
+    let mut no_multiplier_buttons_pressed = true;
+
+    for (_id, gamepad) in model.gilrs.iter().flat_map(Gilrs::gamepads) {
+        no_multiplier_buttons_pressed = !gamepad.is_pressed(Button::DPadUp) &&
+            !gamepad.is_pressed(Button::DPadRight) &&
+            !gamepad.is_pressed(Button::DPadDown) &&
+            !gamepad.is_pressed(Button::DPadLeft);
+    }
+
+    let no_multiplier_keys_pressed = !app.keys.down.contains(&Key::J) &&
+        !app.keys.down.contains(&Key::K) &&
+        !app.keys.down.contains(&Key::L) &&
+        !app.keys.down.contains(&Key::Semicolon);
+
+    if no_multiplier_keys_pressed && no_multiplier_buttons_pressed {
+        model.frequency_multiplier = model.base_frequency_multiplier
+    }
+}
+
+fn view(app: &App, model: &Model, draw: &Draw) {
+    let elapsed = model.start_time.elapsed();
+    let elapsed_secs = elapsed.as_secs_f32();
+    let r = (elapsed_secs * 0.03 * std::f32::consts::PI + 3.0)
+        .sin()
+        .abs()
+        * 0.1;
+    let g = (elapsed_secs * 0.005 * std::f32::consts::PI + 6.0)
+        .sin()
+        .abs()
+        * 0.05;
+    let b = (elapsed_secs * 0.05 * std::f32::consts::PI).sin().abs() * 0.3;
+
+    let alpha = match model.draw_mode {
+        DrawMode::NoTrails => 1.0,
+        DrawMode::Trails => model.oscillator.abs() * 0.3,
+    };
+
+    draw.rect()
+        .wh(app.window_rect().wh())
+        .rgba(r, g, b, alpha);
+
+    let colour = drifting_colour(elapsed_secs, model.agent_alpha);
+    match model.draw_mode {
+        DrawMode::NoTrails => {
+            let mut colour = colour;
+            colour.red = 1.0;
+            model.swarm.draw_trails(draw, colour, 0.5)
+        }
+        DrawMode::Trails => model.swarm.draw_trails(draw, colour, 0.5),
+    }
+
+    view_metro(app, &model.metro, draw);
+}
+
+fn key_released(app: &App, _model: &mut Model, key: Key) {
+    match key {
+        Key::S => {
+            let name = screenshot_name(app);
+            println!("saving screenshot: {}", name);
+            app.main_window().capture_frame(name);
+        }
+        _other_key => {}
+    }
+}
+
+// window events don't carry a timestamp, but their handlers run as the event arrives rather
+// than on the next update, so the clock's current time there is as close as it gets
+fn tap(model: &mut Model, time: Instant) {
+    // calibration taps don't touch the tempo, so there's nothing to lead with
+    let calibrating = model.metro.is_calibrating();
+    model.metro.tap_at(time);
+    if !calibrating {
+        take_lead(model);
+    }
+}
+
+fn toggle_calibration(model: &mut Model) {
+    if !model.metro.is_calibrating() {
+        model.metro.start_calibration();
+        println!("calibrating: tap along to the flash, then C again");
+    } else if model.metro.finish_calibration().is_none() {
+        println!("not enough taps to calibrate");
+    }
+}
+
+fn start_grid(model: &mut Model) {
+    if let Some(grid) = &model.grid {
+        model.metro.load_grid(grid.clone(), model.metro.clock.now());
+        model.automation_start = (Instant::now(), model.metro.visual_beats());
+        take_lead(model);
+    }
+}
+
+// the noise zooms out and churns harder over a bar, then settles back over the next, starting
+// on the "one"
+// plays the sweep on the visual clock. if the tempo goes, or the clock jumps back or more than a
+// bar ahead, there's nothing sensible to play it to, so it's finished where it would have ended
+fn update_sweep(model: &mut Model, tempo_gone: bool) {
+    let now = model.metro.visual_beats();
+    let last = std::mem::replace(&mut model.sweep_beats, now);
+    if let Some(sweep) = model.sweep.take() {
+        let jumped = now < last || now - last > model.metro.meter().beats_per_bar as f64;
+        if tempo_gone || jumped {
+            sweep.finish(model);
+        } else if sweep.update(model, now) {
+            model.sweep = Some(sweep);
+        }
+    }
+}
+
+fn start_sweep(model: &mut Model) {
+    if model.metro.bpm.is_none() {
+        println!("no tempo to sweep to");
+        return;
+    }
+    let bar = model.metro.meter().beats_per_bar as f64;
+    let scale = model.noise_scale as f32;
+    let strength = model.noise_strength as f32;
+    let mut sweep = Timeline::new()
+        .then(Tween::new(scale, scale * 3.0, bar), |m: &mut Model, v| {
+            m.noise_scale = v as f64
+        })
+        .with(
+            Tween::new(strength, strength * 2.0, bar / 2.0).with_ease(Ease::Out(Curve::Back)),
+            |m, v| m.noise_strength = v as f64,
+        )
+        .then(
+            Tween::new(scale * 3.0, scale, bar).with_ease(Ease::InOut(Curve::Expo)),
+            |m, v| m.noise_scale = v as f64,
+        )
+        .with(Tween::new(strength * 2.0, strength, bar), |m, v| {
+            m.noise_strength = v as f64
+        });
+    sweep.start_at(model.metro.next_visual_bar());
+    model.sweep = Some(sweep);
+}
+
+fn next_swing(model: &mut Model) {
+    let groove = &mut model.metro.groove;
+    let next = SWINGS.iter().find(|s| **s > groove.swing).unwrap_or(&SWINGS[0]);
+    groove.swing = *next;
+    println!("swing: {}%", next);
+}
+
+fn accept_tempo_suggestion(model: &mut Model) {
+    model.metro.accept_tempo_suggestion();
+    take_lead(model);
+}
+
+fn nudge_bpm(model: &mut Model, delta: f64) {
+    model.metro.nudge_bpm(delta);
+    take_lead(model);
+}
+
+fn next_meter(model: &mut Model) {
+    let current = METERS.iter().position(|m| *m == model.metro.meter());
+    let next = current.map_or(0, |i| (i + 1) % METERS.len());
+    model.metro.set_meter(METERS[next]);
+    println!("meter: {}", METERS[next]);
+}
+
+fn log_metro_event(event: &MetroEvent) {
+    match event {
+        MetroEvent::TapRegistered { taps, .. } => println!("tap {}", taps),
+        MetroEvent::TempoChanged { bpm: Some(bpm) } => println!("bpm: {:.1}", bpm),
+        MetroEvent::TempoChanged { bpm: None } => println!("no tempo"),
+        MetroEvent::TempoOutOfRange { bpm, suggestion } => println!(
+            "{:.1}bpm could be half or double time; X or select switches to {:.1}",
+            bpm, suggestion
+        ),
+        MetroEvent::TimedOut => println!("tap timeout"),
+        MetroEvent::Cleared => println!("cleared"),
+        MetroEvent::Calibrated { latency } => {
+            println!("latency: {:.0}ms", latency * 1000.0)
+        }
+        MetroEvent::Beat { .. } | MetroEvent::Downbeat { .. } => {}
+    }
+}
+
+fn clear(model: &mut Model) {
+    model.metro.clear();
+    take_lead(model);
+}
+
+// whoever changes the tempo here leads the other machines
+fn take_lead(model: &mut Model) {
+    if let Some(sync) = &mut model.sync {
+        if !sync.is_leader() {
+            sync.take_lead();
+        }
+    }
+}
+
+fn nudge_midi_offset(model: &mut Model, seconds: f64) {
+    if let Some(midi_clock_out) = &model.midi_clock_out {
+        let offset = midi_clock_out.offset() + seconds;
+        midi_clock_out.set_offset(offset);
+        println!("MIDI clock offset: {:.0}ms", offset * 1000.0);
+    }
+}
+
+pub fn mouse_pressed(_app: &App, model: &mut Model, mouse_button: MouseButton) {
+    match mouse_button {
+        MouseButton::Left => tap(model, model.metro.clock.now()),
+        MouseButton::Right => clear(model),
+        _ => {}
+    }
+}
+
+pub fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    match key {
+        // home row fingers
+        Key::F => model.draw_mode = DrawMode::Trails,
+        Key::D => model.draw_mode = DrawMode::NoTrails,
+
+        Key::J =>  model.frequency_multiplier = 1.0/4.0,
+        Key::K =>  model.frequency_multiplier = 1.0/2.0,
+        Key::L =>  model.frequency_multiplier = 2.0,
+        Key::Semicolon =>  model.frequency_multiplier = 4.0,
+
+        Key::Space => tap(model, model.metro.clock.now()),
+        // hit play on the track at the same time
+        Key::Return => start_grid(model),
+        // the "one", and the time signature it starts bars of
+        Key::O => model.metro.set_downbeat(),
+        Key::M => next_meter(model),
+        Key::U => {
+            model.metro.tap_unit = model.metro.tap_unit.next();
+            println!("tapping {} notes", model.metro.tap_unit);
+        }
+        Key::X => accept_tempo_suggestion(model),
+        Key::W => next_swing(model),
+        Key::C => toggle_calibration(model),
+        Key::G => start_sweep(model),
+        Key::R => {
+            model.metro.resync();
+            take_lead(model);
+        }
+
+        // fine tempo up and down; holding left or right bends the beat instead
+        Key::Up => nudge_bpm(model, BPM_NUDGE),
+        Key::Down => nudge_bpm(model, -BPM_NUDGE),
+
+        // nudge how far ahead MIDI clock goes out
+        Key::LBracket => nudge_midi_offset(model, -0.005),
+        Key::RBracket => nudge_midi_offset(model, 0.005),
+        Key::Back => clear(model),
+        Key::Delete => clear(model),
+        Key::Q => app.quit(),
+        _ => {}
+    }
+}
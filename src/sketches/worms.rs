@@ -0,0 +1,246 @@
+use nannou::prelude::*;
+
+use crate::sketch::{screenshot_name, Sketch};
+use gilrs::{Gilrs, Event, EventType, Button};
+
+use crate::agents::{drifting_colour, Heading, Steering, Swarm};
+use crate::field::{self, Generator};
+
+const DEADZONE: f32 = 0.125;
+
+pub struct Model {
+    swarm: Swarm,
+    noise_scale: f64,
+    noise_strength: f64,
+    noise_z_velocity: f64,
+    agent_alpha: f32,
+    draw_mode: DrawMode,
+    field: Generator,
+    start_time: std::time::Instant,
+    oscillator: f32,
+    oscillator_old: f32,
+    oscillator_above_0: bool,
+    oscillator_above_0_old: bool,
+    frequency_multiplier: f32,
+    gilrs: Option<Gilrs>,
+    stick_y: f32,
+}
+
+impl Sketch for Model {
+    fn new(app: &App, args: &[String]) -> Model {
+        model(app, args)
+    }
+
+    fn size() -> (u32, u32) {
+        (1920, 1080)
+    }
+
+    fn update(&mut self, app: &App, frame_update: Update) {
+        update(app, self, frame_update);
+    }
+
+    fn view(&self, app: &App, draw: &Draw) {
+        view(app, self, draw);
+    }
+
+    fn field_mut(&mut self) -> Option<&mut Generator> {
+        Some(&mut self.field)
+    }
+
+    fn key_pressed(&mut self, app: &App, key: Key) {
+        key_pressed(app, self, key);
+    }
+
+    fn key_released(&mut self, app: &App, key: Key) {
+        key_released(app, self, key);
+    }
+
+    fn mouse_pressed(&mut self, app: &App, button: MouseButton) {
+        mouse_pressed(app, self, button);
+    }
+}
+
+
+fn model(app: &App, _args: &[String]) -> Model {
+    let gilrs = Gilrs::new()
+        .map_err(|e| println!("no gamepads: {}", e))
+        .ok();
+
+    let noise_z_range = 0.4;
+    let agent_count = 300;
+    let swarm = Swarm::new(agent_count, app.window_rect(), noise_z_range, 0.3..6.0)
+        .with_heading(Heading::Up);
+
+    let oscillator = 0.0;
+
+    Model {
+        swarm,
+        noise_scale: 60.0,
+        noise_strength: 1.0,
+        noise_z_velocity: 0.01,
+        agent_alpha: 0.125,
+        draw_mode: DrawMode::Trails,
+        field: Generator::new(field::Kind::Perlin, 12),
+        oscillator,
+        oscillator_old: oscillator,
+        oscillator_above_0: true,
+        oscillator_above_0_old: true,
+        frequency_multiplier: 1.0,
+        start_time: std::time::Instant::now(),
+        gilrs,
+        stick_y: 0.0,
+    }
+}
+
+
+enum DrawMode {
+    NoTrails,
+    Trails,
+}
+
+fn update(app: &App, model: &mut Model, _frame_update: Update) {
+    let elapsed = model.start_time.elapsed();
+    let elapsed_secs = elapsed.as_secs_f32();
+    let z = elapsed.as_secs_f64() * 0.5;
+
+    let bounds = app.window_rect();
+
+    model.oscillator_old = model.oscillator;
+    model.oscillator_above_0_old = model.oscillator_above_0;
+    model.oscillator = model.stick_y * 2.0;
+
+    while let Some(Event { id: _, event, time: _ }) = model.gilrs.as_mut().and_then(Gilrs::next_event) {
+        match event {
+            EventType::AxisChanged(gilrs::Axis::RightStickY, value, _) => {
+                if value.abs() > DEADZONE {
+                    model.stick_y = value;
+                } else {
+                    model.stick_y = 0.0;
+                }
+            }
+
+            EventType::ButtonPressed(button, _) => {
+                match button {
+                    Button::South => { tap(model) },
+
+                    Button::DPadUp => { model.frequency_multiplier = 4.0 },
+                    Button::DPadRight => { model.frequency_multiplier = 2.0 },
+                    Button::DPadDown => { model.frequency_multiplier = 1.0/4.0 },
+                    Button::DPadLeft => { model.frequency_multiplier = 1.0/2.0 },
+
+                    _ => {
+                    },
+                }
+            },
+
+            _ => {}
+        }
+    }
+
+    // model.oscillator = model.stick_y;
+    model.oscillator += 0.2;
+
+    model.oscillator_above_0 = model.oscillator > 0.0;
+
+    if model.oscillator_above_0 && model.oscillator_above_0 != model.oscillator_above_0_old {
+        model.field.set_seed((random_f32() * 10000.0).floor() as u32);
+    }
+
+    let steering = Steering {
+        z: Some(z),
+        scroll: elapsed_secs as f64,
+        ..Steering::new(model.noise_scale, model.noise_strength)
+    };
+    model.swarm.steer(&model.field, &steering);
+    model.swarm.step(model.oscillator, model.noise_z_velocity, bounds);
+
+
+    let mut no_multiplier_buttons_pressed = true;
+
+    for (_id, gamepad) in model.gilrs.iter().flat_map(Gilrs::gamepads) {
+        no_multiplier_buttons_pressed = !gamepad.is_pressed(Button::DPadUp) &&
+            !gamepad.is_pressed(Button::DPadRight) &&
+            !gamepad.is_pressed(Button::DPadDown) &&
+            !gamepad.is_pressed(Button::DPadLeft);
+    }
+
+    let no_multiplier_keys_pressed = !app.keys.down.contains(&Key::J) &&
+        !app.keys.down.contains(&Key::K) &&
+        !app.keys.down.contains(&Key::L) &&
+        !app.keys.down.contains(&Key::Semicolon);
+
+    if no_multiplier_keys_pressed && no_multiplier_buttons_pressed {
+        model.frequency_multiplier = 1.0
+    }
+}
+
+fn view(app: &App, model: &Model, draw: &Draw) {
+    let elapsed = model.start_time.elapsed();
+    let elapsed_secs = elapsed.as_secs_f32();
+    let r = (elapsed_secs * 0.03 * std::f32::consts::PI + 3.0)
+        .sin()
+        .abs()
+        * 0.1;
+    let g = (elapsed_secs * 0.005 * std::f32::consts::PI + 6.0)
+        .sin()
+        .abs()
+        * 0.05;
+    let b = (elapsed_secs * 0.05 * std::f32::consts::PI).sin().abs() * 0.3;
+
+    let alpha = match model.draw_mode {
+        DrawMode::NoTrails => 1.0,
+        DrawMode::Trails => model.oscillator.abs() * 0.3,
+    };
+
+    draw.rect()
+        .wh(app.window_rect().wh())
+        .rgba(r, g, b, alpha);
+
+    let colour = drifting_colour(elapsed_secs, model.agent_alpha);
+    match model.draw_mode {
+        DrawMode::NoTrails => {
+            let mut colour = colour;
+            colour.red = 1.0;
+            model.swarm.draw_trails(draw, colour, 0.5)
+        }
+        DrawMode::Trails => model.swarm.draw_trails(draw, colour, 0.5),
+    }
+}
+
+fn key_released(app: &App, _model: &mut Model, key: Key) {
+    match key {
+        Key::S => {
+            let name = screenshot_name(app);
+            println!("saving screenshot: {}", name);
+            app.main_window().capture_frame(name);
+        }
+        _other_key => {}
+    }
+}
+
+fn tap(_model: &mut Model) {
+}
+
+pub fn mouse_pressed(_app: &App, model: &mut Model, mouse_button: MouseButton) {
+    if mouse_button == MouseButton::Left {
+        tap(model);
+    }
+}
+
+pub fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    match key {
+        // home row fingers
+        Key::F => model.draw_mode = DrawMode::Trails,
+        Key::D => model.draw_mode = DrawMode::NoTrails,
+
+        Key::J =>  model.frequency_multiplier = 1.0/4.0,
+        Key::K =>  model.frequency_multiplier = 1.0/2.0,
+        Key::L =>  model.frequency_multiplier = 2.0,
+        Key::Semicolon =>  model.frequency_multiplier = 4.0,
+
+        Key::Space => tap(model),
+        Key::Escape =>  app.quit(),
+        Key::Q => app.quit(),
+        _ => {}
+    }
+}
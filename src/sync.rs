@@ -0,0 +1,381 @@
+// Tempo, phase and noise field sync between machines on the same network.
+//
+// Every machine multicasts a small heartbeat with its state. One of them leads and the rest
+// follow it: whoever most recently took the lead (by tapping, say) wins, with ties going to
+// the higher node id. Followers adopt the leader's term, so if the leader drops off the
+// network the next in line carries on from the same state.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::field;
+
+pub const GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 42, 99);
+pub const PORT: u16 = 4299;
+
+const MAGIC: &[u8; 4] = b"DLYS";
+const VERSION: u8 = 2;
+const PACKET_SIZE: usize = 43;
+const HEARTBEAT: Duration = Duration::from_millis(50);
+const PEER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What gets shared between machines. `beats` carries the bar position along with it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct State {
+    pub bpm: Option<f64>,
+    pub beats: f64,
+    pub noise_seed: u32,
+    pub field_kind: field::Kind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Packet {
+    node: u64,
+    term: u64,
+    leading: bool,
+    state: State,
+}
+
+impl Packet {
+    fn encode(&self) -> [u8; PACKET_SIZE] {
+        let mut bytes = [0; PACKET_SIZE];
+        bytes[0..4].copy_from_slice(MAGIC);
+        bytes[4] = VERSION;
+        bytes[5] = self.leading as u8;
+        bytes[6..14].copy_from_slice(&self.node.to_le_bytes());
+        bytes[14..22].copy_from_slice(&self.term.to_le_bytes());
+        bytes[22..30].copy_from_slice(&self.state.bpm.unwrap_or(f64::NAN).to_le_bytes());
+        bytes[30..38].copy_from_slice(&self.state.beats.to_le_bytes());
+        bytes[38..42].copy_from_slice(&self.state.noise_seed.to_le_bytes());
+        bytes[42] = self.state.field_kind.index() as u8;
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Packet> {
+        if bytes.len() != PACKET_SIZE || &bytes[0..4] != MAGIC || bytes[4] != VERSION {
+            return None;
+        }
+
+        let bpm = f64::from_le_bytes(bytes[22..30].try_into().ok()?);
+        Some(Packet {
+            node: u64::from_le_bytes(bytes[6..14].try_into().ok()?),
+            term: u64::from_le_bytes(bytes[14..22].try_into().ok()?),
+            leading: bytes[5] != 0,
+            state: State {
+                bpm: if bpm.is_nan() { None } else { Some(bpm) },
+                beats: f64::from_le_bytes(bytes[30..38].try_into().ok()?),
+                noise_seed: u32::from_le_bytes(bytes[38..42].try_into().ok()?),
+                field_kind: *field::Kind::ALL.get(bytes[42] as usize)?,
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Peer {
+    term: u64,
+    leading: bool,
+    state: State,
+    last_seen: Instant,
+}
+
+// Who leads, decided from the heartbeats heard so far. Kept apart from the socket so the
+// election can be run by hand.
+#[derive(Debug)]
+struct Election {
+    node: u64,
+    term: u64,
+    leading: bool,
+    peers: HashMap<u64, Peer>,
+}
+
+impl Election {
+    fn new(node: u64) -> Election {
+        Election {
+            node,
+            term: 0,
+            leading: false,
+            peers: HashMap::new(),
+        }
+    }
+
+    fn take_lead(&mut self) {
+        let highest_term = self.peers.values().map(|p| p.term).max().unwrap_or(0);
+        self.term = self.term.max(highest_term) + 1;
+        self.leading = true;
+    }
+
+    fn is_leader(&self) -> bool {
+        self.leader().is_none()
+    }
+
+    // the peer to follow, or None when this machine leads. A machine that's already leading
+    // outranks one that has only caught up with its term.
+    fn leader(&self) -> Option<(&u64, &Peer)> {
+        let rank = (self.leading, self.term, self.node);
+        self.peers
+            .iter()
+            .filter(|(node, peer)| (peer.leading, peer.term, **node) > rank)
+            .max_by_key(|(node, peer)| (peer.leading, peer.term, **node))
+    }
+
+    fn receive(&mut self, packet: Packet, time: Instant) {
+        if packet.node == self.node {
+            return;
+        }
+        self.peers.insert(
+            packet.node,
+            Peer {
+                term: packet.term,
+                leading: packet.leading,
+                state: packet.state,
+                last_seen: time,
+            },
+        );
+    }
+
+    fn forget_missing_peers(&mut self, time: Instant) {
+        self.peers
+            .retain(|_, peer| time.saturating_duration_since(peer.last_seen) < PEER_TIMEOUT);
+    }
+
+    // the leader's state as of `time`, if there's a leader to follow
+    fn follow(&mut self, time: Instant) -> Option<State> {
+        let leader = self
+            .leader()
+            .map(|(_, peer)| (peer.term, peer.state, peer.last_seen));
+        // nobody outranks this machine, so it leads until somebody does
+        self.leading = leader.is_none();
+        let (term, state, last_seen) = leader?;
+        self.term = term;
+
+        let seconds = time.saturating_duration_since(last_seen).as_secs_f64();
+        let beats = state.beats + state.bpm.map_or(0.0, |bpm| seconds * bpm / 60.0);
+        Some(State { beats, ..state })
+    }
+
+    // what to tell the others about this machine
+    fn packet(&self, state: State) -> Packet {
+        Packet {
+            node: self.node,
+            term: self.term,
+            leading: self.leading,
+            state,
+        }
+    }
+}
+
+pub struct SyncGroup {
+    socket: UdpSocket,
+    destination: SocketAddr,
+    election: Election,
+    last_sent: Option<(Instant, State)>,
+}
+
+impl SyncGroup {
+    /// Join the default sync group.
+    pub fn join() -> io::Result<SyncGroup> {
+        SyncGroup::join_group(GROUP, PORT)
+    }
+
+    /// Join a multicast group. Any number of processes on one machine can join the same group,
+    /// which is handy for trying things out on localhost.
+    pub fn join_group(group: Ipv4Addr, port: u16) -> io::Result<SyncGroup> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port).into())?;
+        socket.join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)?;
+        socket.set_multicast_loop_v4(true)?;
+        // stay on the local network
+        socket.set_multicast_ttl_v4(1)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(SyncGroup {
+            socket: socket.into(),
+            destination: SocketAddrV4::new(group, port).into(),
+            election: Election::new(nannou::rand::random()),
+            last_sent: None,
+        })
+    }
+
+    /// Make this machine the leader, e.g. because someone tapped a new tempo into it.
+    pub fn take_lead(&mut self) {
+        self.election.take_lead();
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.election.is_leader()
+    }
+
+    pub fn peer_count(&self) -> usize {
+        self.election.peers.len()
+    }
+
+    fn should_send(&self, state: State, time: Instant) -> bool {
+        match self.last_sent {
+            None => true,
+            Some((sent_at, sent)) => {
+                let changed = sent.bpm != state.bpm
+                    || sent.noise_seed != state.noise_seed
+                    || sent.field_kind != state.field_kind;
+                // followers only need to be heard for the election
+                (changed && self.is_leader()) || time.saturating_duration_since(sent_at) >= HEARTBEAT
+            }
+        }
+    }
+
+    /// Share this machine's `state` and hear from the others. Returns the leader's state when
+    /// there's another machine to follow.
+    pub fn update(&mut self, state: State) -> Option<State> {
+        let mut buffer = [0; 64];
+        while let Ok(length) = self.socket.recv(&mut buffer) {
+            if let Some(packet) = Packet::decode(&buffer[..length]) {
+                self.election.receive(packet, Instant::now());
+            }
+        }
+
+        let now = Instant::now();
+        self.election.forget_missing_peers(now);
+        let leader_state = self.election.follow(now);
+
+        if self.should_send(state, now) {
+            let packet = self.election.packet(state);
+            match self.socket.send_to(&packet.encode(), self.destination) {
+                Ok(_) => self.last_sent = Some((now, state)),
+                Err(e) => println!("sync send failed: {}", e),
+            }
+        }
+
+        leader_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(bpm: f64, beats: f64, noise_seed: u32) -> State {
+        State {
+            bpm: Some(bpm),
+            beats,
+            noise_seed,
+            field_kind: field::Kind::Perlin,
+        }
+    }
+
+    #[test]
+    fn packets_round_trip() {
+        let packet = Packet {
+            node: 42,
+            term: 7,
+            leading: true,
+            state: state(128.0, 17.25, 9001),
+        };
+        assert_eq!(Packet::decode(&packet.encode()), Some(packet));
+
+        let no_tempo = Packet {
+            state: State {
+                bpm: None,
+                ..packet.state
+            },
+            ..packet
+        };
+        assert_eq!(Packet::decode(&no_tempo.encode()), Some(no_tempo));
+
+        let curl = Packet {
+            state: State {
+                field_kind: field::Kind::Curl,
+                ..packet.state
+            },
+            ..packet
+        };
+        assert_eq!(Packet::decode(&curl.encode()), Some(curl));
+
+        assert_eq!(Packet::decode(b"hello"), None);
+        let mut unknown_kind = packet.encode();
+        unknown_kind[42] = 99;
+        assert_eq!(Packet::decode(&unknown_kind), None);
+    }
+
+    #[test]
+    fn highest_term_leads_and_ties_go_to_the_higher_node() {
+        let mut sync = Election::new(5);
+        let now = Instant::now();
+        assert!(sync.is_leader());
+
+        sync.receive(Packet { node: 3, term: 0, leading: false, state: state(120.0, 0.0, 1) }, now);
+        assert!(sync.is_leader());
+
+        sync.receive(Packet { node: 9, term: 0, leading: false, state: state(120.0, 0.0, 2) }, now);
+        assert!(!sync.is_leader());
+        assert_eq!(sync.follow(now).unwrap().noise_seed, 2);
+
+        sync.take_lead();
+        assert!(sync.is_leader());
+        assert_eq!(sync.follow(now), None);
+
+        sync.receive(Packet { node: 3, term: 2, leading: true, state: state(120.0, 0.0, 3) }, now);
+        assert_eq!(sync.follow(now).unwrap().noise_seed, 3);
+        // following keeps this machine in line to take over, without outranking the leader
+        assert_eq!(sync.term, 2);
+        assert!(!sync.leading);
+        assert!(!sync.is_leader());
+    }
+
+    #[test]
+    fn followers_extrapolate_and_take_over_when_the_leader_goes() {
+        let mut sync = Election::new(1);
+        let now = Instant::now();
+        sync.receive(Packet { node: 2, term: 1, leading: true, state: state(120.0, 8.0, 1) }, now);
+
+        let later = now + Duration::from_millis(250);
+        assert!((sync.follow(later).unwrap().beats - 8.5).abs() < 1e-9);
+
+        sync.forget_missing_peers(now + PEER_TIMEOUT);
+        assert_eq!(sync.follow(now + PEER_TIMEOUT), None);
+        assert!(sync.is_leader());
+        assert!(sync.leading);
+    }
+
+    #[test]
+    #[ignore = "needs multicast on the loopback interface"]
+    fn machines_on_one_host_follow_the_leader() {
+        let port = 14302;
+        let mut leader = SyncGroup::join_group(GROUP, port).unwrap();
+        let mut follower = SyncGroup::join_group(GROUP, port).unwrap();
+        leader.take_lead();
+
+        let leading = State {
+            field_kind: field::Kind::Curl,
+            ..state(126.0, 32.0, 4242)
+        };
+        let quiet = State {
+            bpm: None,
+            beats: 0.0,
+            noise_seed: 0,
+            field_kind: field::Kind::Perlin,
+        };
+
+        let mut followed = None;
+        for _ in 0..100 {
+            leader.update(leading);
+            followed = follower.update(quiet).or(followed);
+            if followed.is_some() && follower.peer_count() > 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let followed = followed.expect("no word from the leader");
+        assert_eq!(followed.bpm, Some(126.0));
+        assert_eq!(followed.noise_seed, 4242);
+        assert_eq!(followed.field_kind, field::Kind::Curl);
+        assert!(followed.beats >= 32.0);
+        assert!(leader.is_leader());
+        assert!(!follower.is_leader());
+    }
+}
@@ -1,11 +1,50 @@
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 
 use nannou::prelude::*;
 
+use crate::beats::BeatGrid;
+
 pub mod spring;
 use spring::Spring;
 
+pub mod tempo;
+
+pub mod clock;
+use clock::{Clock, MonotonicClock};
+
+pub mod meter;
+use meter::Meter;
+
+pub mod ramp;
+use ramp::Ramp;
+
+pub mod event;
+use event::{Event, Events};
+
+pub mod unit;
+use unit::TapUnit;
+
+pub mod groove;
+use groove::Groove;
+
+pub mod latency;
+use latency::Calibration;
+
 const WAIT_CUTOFF: f64 = 2.0;
+// only the most recent taps count towards the tempo
+const TAP_WINDOW: usize = 8;
+// how quickly (per second) a tap pulls the beat clock back into line
+const PHASE_CORRECTION_RATE: f64 = 4.0;
+// how long a newly tapped tempo takes to glide in
+const DEFAULT_RAMP_BEATS: f64 = 2.0;
+// tempo changes smaller than this aren't worth an event, which keeps MIDI clock jitter quiet
+const TEMPO_EVENT_RESOLUTION: f64 = 0.1;
+// tempos outside this are probably being counted in half or double time
+const DEFAULT_TEMPO_RANGE: (f64, f64) = (80.0, 160.0);
+// nudging can't slow the tempo down any further than this
+const MIN_BPM: f64 = 1.0;
+// the beat to flash when calibrating without a tempo
+const CALIBRATION_BPM: f64 = 120.0;
 
 #[derive(Debug, PartialEq)]
 pub enum TapTempoState {
@@ -14,68 +53,448 @@ pub enum TapTempoState {
     RecordingTaps,
 }
 
-pub struct Model {
+pub struct Model<C: Clock = MonotonicClock> {
+    pub clock: C,
     state: TapTempoState,
     pub taps: Vec<Instant>,
     pub seconds_since_last_tap: Option<f64>,
+    /// the tempo the beat clock is running at right now, which can be partway through a ramp
     pub bpm: Option<f64>,
+    /// beats it takes a newly tapped tempo to glide in over; 0 switches straight to it
+    pub ramp_beats: f64,
+    /// the note value being tapped
+    pub tap_unit: TapUnit,
+    // tempos outside this range get a half or double time suggestion
+    tempo_range: (f64, f64),
+    /// swing and feel for `grooved_beats` and `swung_phase`
+    pub groove: Groove,
+    /// seconds the picture trails the sound by. `visual_beats` and everything built on it run
+    /// this far ahead to make up for it; the beat and bar counters, the `_this_frame` flags,
+    /// `beat_in_bar` and the `Beat` and `Downbeat` events stay on the beat as it's heard, for
+    /// the things that go out with the sound, like MIDI clock
+    pub latency: f64,
+    /// how steady the recent taps were, 0..1
+    pub confidence: Option<f64>,
+    /// RMS timing error of the recent taps, in seconds
+    pub jitter: Option<f64>,
+    /// position within the current beat, 0..1
+    pub phase: f64,
+    /// beats counted since the tempo was set
+    pub beat: u64,
+    /// whole bars since the "one" (see `set_downbeat`), or 0 before it
+    pub bar: u64,
+    meter: Meter,
+    // a beat that fell on the "one"; every bar starts a whole number of bars from it
+    downbeat: u64,
+    /// true on the frame where `phase` wrapped around
+    pub beat_this_frame: bool,
+    /// true on the frame where a new bar started
+    pub bar_this_frame: bool,
+    phase_error: f64,
+    ramp: Option<Ramp>,
+    events: Events,
+    // the tempo in the last `TempoChanged`
+    reported_bpm: Option<f64>,
+    // how much faster than the tempo the clock is being pushed
+    bend: f64,
+    last_update: Option<Instant>,
+    // a track's beat grid and when the track started playing
+    grid: Option<(BeatGrid, Instant)>,
+    // taps go here instead of into the tempo while calibrating
+    calibration: Option<Calibration>,
+    // whether the flash's tempo was put on a clock that didn't have one, to be taken off again
+    calibration_bpm: bool,
     spring: Spring,
 }
 
-impl Model {
-    pub fn default() -> Model {
+impl Default for Model {
+    fn default() -> Model {
+        Model::with_clock(MonotonicClock)
+    }
+}
+
+impl<C: Clock> Model<C> {
+    pub fn with_clock(clock: C) -> Model<C> {
         Model {
+            clock,
             state: TapTempoState::Inactive,
             taps: Vec::new(),
             seconds_since_last_tap: None,
             bpm: None,
-            spring: Spring::new(9.0, 0.3, 0.99, 0.0, 0.0),
+            ramp_beats: DEFAULT_RAMP_BEATS,
+            tap_unit: TapUnit::default(),
+            tempo_range: DEFAULT_TEMPO_RANGE,
+            groove: Groove::default(),
+            latency: 0.0,
+            confidence: None,
+            jitter: None,
+            phase: 0.0,
+            beat: 0,
+            bar: 0,
+            meter: Meter::default(),
+            downbeat: 0,
+            beat_this_frame: false,
+            bar_this_frame: false,
+            phase_error: 0.0,
+            ramp: None,
+            events: Events::default(),
+            reported_bpm: None,
+            bend: 0.0,
+            last_update: None,
+            grid: None,
+            calibration: None,
+            calibration_bpm: false,
+            spring: Spring::wobbly(0.0),
         }
     }
 
-    fn calculate_average_interval(&self) -> Option<f64> {
-        if self.taps.len() < 2 {
-            return None;
+    fn calculate_estimate(&self) -> Option<tempo::Estimate> {
+        let first_tap = *self.taps.first()?;
+        let times: Vec<f64> = self
+            .taps
+            .iter()
+            .map(|t| t.duration_since(first_tap).as_secs_f64())
+            .collect();
+        tempo::estimate(&times)
+    }
+
+    /// Convert a wall-clock event time (like the one on `gilrs::Event`) to this model's clock.
+    pub fn event_instant(&self, time: SystemTime) -> Instant {
+        let age = SystemTime::now().duration_since(time).unwrap_or_default();
+        let now = self.clock.now();
+        now.checked_sub(age).unwrap_or(now)
+    }
+
+    /// Beats elapsed since the tempo was set, including the fraction of the current beat.
+    pub fn beats(&self) -> f64 {
+        self.beat as f64 + self.phase
+    }
+
+    /// `beats()` shifted ahead by `latency`, for anything that gets drawn: by the time the
+    /// frame makes it onto the screen, this is where the beat will be.
+    pub fn visual_beats(&self) -> f64 {
+        self.beats() + self.latency * self.bpm.unwrap_or(0.0) / 60.0
+    }
+
+    /// The bar `visual_beats` is in.
+    pub fn visual_bar(&self) -> u64 {
+        self.bar_at(self.visual_beats().max(0.0) as u64)
+    }
+
+    /// `visual_beats()` with the groove applied, for things that should pulse with the swing.
+    pub fn grooved_beats(&self) -> f64 {
+        let one = self.downbeat as f64;
+        self.groove.apply(self.visual_beats() - one) + one
+    }
+
+    /// Position within the current swung eighth or sixteenth, 0..1.
+    pub fn swung_phase(&self) -> f64 {
+        let steps_per_beat = self.groove.subdivision.steps_per_beat() as f64;
+        (self.grooved_beats() * steps_per_beat).rem_euclid(1.0)
+    }
+
+    pub fn meter(&self) -> Meter {
+        self.meter
+    }
+
+    /// Change the time signature, keeping the current "one".
+    pub fn set_meter(&mut self, meter: Meter) {
+        self.meter = meter;
+        self.bar = self.bar_at(self.beat);
+    }
+
+    /// Which beat of the bar the clock is on, counting the "one" as 0.
+    pub fn beat_in_bar(&self) -> u64 {
+        self.beat_in_bar_at(self.beat)
+    }
+
+    fn beat_in_bar_at(&self, beat: u64) -> u64 {
+        (beat as i64 - self.downbeat as i64).rem_euclid(self.meter.beats_per_bar as i64) as u64
+    }
+
+    /// `beats()` at the start of the next bar, for lining things up with the "one".
+    pub fn next_bar(&self) -> f64 {
+        (self.beat + self.meter.beats_per_bar - self.beat_in_bar()) as f64
+    }
+
+    /// `visual_beats()` at the start of the next bar, for lining up what gets drawn with the
+    /// "one".
+    pub fn next_visual_bar(&self) -> f64 {
+        let beat = self.visual_beats().max(0.0) as u64;
+        (beat + self.meter.beats_per_bar - self.beat_in_bar_at(beat)) as f64
+    }
+
+    fn bar_at(&self, beat: u64) -> u64 {
+        let bars = (beat as i64 - self.downbeat as i64).div_euclid(self.meter.beats_per_bar as i64);
+        bars.max(0) as u64
+    }
+
+    pub fn set_downbeat(&mut self) {
+        self.set_downbeat_at(self.clock.now());
+    }
+
+    /// Make the beat nearest `time` the "one". Tempo and phase carry on as they were.
+    pub fn set_downbeat_at(&mut self, time: Instant) {
+        self.downbeat = self.beats_at(time).round().max(0.0) as u64;
+        self.bar = self.bar_at(self.beat);
+    }
+
+    // the beat clock extrapolated to `time` at the current tempo
+    fn beats_at(&self, time: Instant) -> f64 {
+        let since_update = match self.last_update {
+            Some(t) if time >= t => time.duration_since(t).as_secs_f64(),
+            Some(t) => -t.duration_since(time).as_secs_f64(),
+            None => 0.0,
+        };
+        self.beats() + since_update * self.bpm.unwrap_or(0.0) / 60.0
+    }
+
+    /// Fine-tune the tempo without touching where the beat is.
+    pub fn nudge_bpm(&mut self, delta: f64) {
+        self.grid = None;
+        if let Some(ramp) = &mut self.ramp {
+            ramp.to = (ramp.to + delta).max(MIN_BPM);
+        } else if let Some(bpm) = self.bpm {
+            self.bpm = Some((bpm + delta).max(MIN_BPM));
         }
+    }
 
-        let mut intervals = Vec::new();
-        for i in 1..self.taps.len() {
-            let duration = self.taps[i].duration_since(self.taps[i - 1]);
-            intervals.push(duration.as_secs_f64());
+    /// Run the beat clock this fraction faster (or slower, when negative) than the tempo, like
+    /// pushing on a turntable. Set it back to 0 to let go.
+    pub fn set_bend(&mut self, bend: f64) {
+        if bend != 0.0 {
+            self.grid = None;
         }
+        self.bend = bend;
+    }
 
-        let average_interval = intervals.iter().sum::<f64>() / intervals.len() as f64;
-        Some(average_interval)
+    pub fn resync(&mut self) {
+        self.resync_at(self.clock.now());
     }
 
-    fn calculate_bpm(&self) -> Option<f64> {
-        let average_interval = self.calculate_average_interval();
-        match average_interval {
-            Some(i) => Some(60.0 / i),
-            None => None,
-        }
+    /// Put the "one" right at `time`, shifting the phase to match, without changing the tempo.
+    pub fn resync_at(&mut self, time: Instant) {
+        self.grid = None;
+        let beats = self.beats_at(time);
+        let one = beats.round().max(0.0);
+        self.downbeat = one as u64;
+        self.jump_to(self.beats() + one - beats);
     }
 
     fn timeout(&mut self) {
         self.state = TapTempoState::Inactive;
-        println!("tap timeout");
+        self.events.emit(Event::TimedOut);
     }
 
     fn set_bpm(&mut self, bpm: f64) {
+        if let Some(&tap_time) = self.taps.last() {
+            match self.bpm {
+                Some(old_bpm) => self.align_phase_to_tap(tap_time, old_bpm),
+                // the tap that established the tempo is the beat
+                None => {
+                    self.phase = 0.0;
+                    self.phase_error = 0.0;
+                    self.last_update = Some(tap_time);
+                }
+            }
+        }
+        match self.bpm {
+            // the beat clock speeds up or slows down to the new tempo, never skipping
+            Some(current) if self.ramp_beats > 0.0 => {
+                self.ramp = Some(Ramp::new(current, bpm, self.ramp_beats))
+            }
+            _ => {
+                self.ramp = None;
+                self.bpm = Some(bpm);
+            }
+        }
+    }
+
+    /// The tempo the beat clock is heading for, once any ramp has finished.
+    pub fn target_bpm(&self) -> Option<f64> {
+        self.ramp.as_ref().map(|r| r.to).or(self.bpm)
+    }
+
+    // taps land on beats (or whatever note is being tapped), so steer the clock towards the
+    // nearest one instead of resetting it
+    fn align_phase_to_tap(&mut self, tap_time: Instant, bpm: f64) {
+        let since_update = self
+            .last_update
+            .map_or(0.0, |t| tap_time.saturating_duration_since(t).as_secs_f64());
+        let beats = self.beats() + since_update * bpm / 60.0;
+        let unit = self.tap_unit.quarters();
+        self.phase_error = (beats / unit).round() * unit - beats;
+    }
+
+    pub fn tempo_range(&self) -> (f64, f64) {
+        self.tempo_range
+    }
+
+    /// Tempos outside `low..=high` get a half or double time suggestion. It has to be a range
+    /// of real tempos, above 0 and low to high; anything else is turned down.
+    pub fn set_tempo_range(&mut self, low: f64, high: f64) -> Result<(), String> {
+        if !(low > 0.0 && low < high && high.is_finite()) {
+            return Err(format!("bad tempo range {}..{}", low, high));
+        }
+        self.tempo_range = (low, high);
+        Ok(())
+    }
+
+    /// The tempo in half or double time (or quarter, or quadruple...) that lands in
+    /// `tempo_range`, when the current one doesn't.
+    pub fn tempo_suggestion(&self) -> Option<f64> {
+        let bpm = self.target_bpm()?;
+        let (low, high) = self.tempo_range;
+        // no amount of doubling gets anywhere from these
+        if (bpm >= low && bpm <= high) || !(bpm > 0.0 && bpm.is_finite()) {
+            return None;
+        }
+        let mut suggestion = bpm;
+        while suggestion < low {
+            suggestion *= 2.0;
+        }
+        while suggestion > high {
+            suggestion /= 2.0;
+        }
+        // a range narrower than an octave can be jumped right over
+        (suggestion >= low).then_some(suggestion)
+    }
+
+    /// Switch to the suggested half or double time tempo. The beat count carries on from
+    /// where it is.
+    pub fn accept_tempo_suggestion(&mut self) {
+        if let Some(bpm) = self.tempo_suggestion() {
+            self.grid = None;
+            self.ramp = None;
+            self.bpm = Some(bpm);
+        }
+    }
+
+    /// Follow an external clock (like MIDI) that knows both the tempo and where the beat is.
+    /// Small differences get steered out the same way taps are, big ones jump straight there.
+    pub fn sync(&mut self, bpm: f64, beats: f64) {
+        self.ramp = None;
         self.bpm = Some(bpm);
-        println!("bpm: {}", bpm);
+        let error = beats - self.beats();
+        if error.abs() < 0.5 {
+            self.phase_error = error;
+        } else {
+            self.jump_to(beats);
+        }
+    }
+
+    /// Play along to a track that was analysed ahead of time (see `beats::analyze`) and started
+    /// playing at `start`. The beat clock follows the grid until it's cleared, or until someone
+    /// takes over by hand: tapping, nudging, bending, resyncing or accepting a tempo suggestion
+    /// drops the grid, and the clock carries on from wherever it had got to.
+    pub fn load_grid(&mut self, grid: BeatGrid, start: Instant) {
+        self.clear();
+        self.grid = Some((grid, start));
+    }
+
+    fn follow_grid(&mut self, time: Instant) {
+        let (bpm, beats) = match &self.grid {
+            Some((grid, start)) => {
+                let seconds = if time >= *start {
+                    time.duration_since(*start).as_secs_f64()
+                } else {
+                    -start.duration_since(time).as_secs_f64()
+                };
+                (grid.bpm_at(seconds), grid.beats_at(seconds))
+            }
+            None => return,
+        };
+        self.sync(bpm, beats);
+    }
+
+    // Move the clock straight to `beats`. Landing on a beat or skipping past one counts as that
+    // beat, and likewise for bars, but only once: jumping a song position ahead isn't a flurry
+    // of every beat in between.
+    fn jump_to(&mut self, beats: f64) {
+        let beats = beats.max(0.0);
+        let old_beats = self.beats();
+        self.beat = beats.floor() as u64;
+        self.bar = self.bar_at(self.beat);
+        self.phase = beats.fract();
+        self.phase_error = 0.0;
+
+        let old_beat = old_beats.floor() as u64;
+        if self.beat == old_beat && !(self.phase == 0.0 && beats != old_beats) {
+            return;
+        }
+        self.beat_this_frame = true;
+        // bars counted from the current "one", which a resync has just moved
+        if self.bar != self.bar_at(old_beat) || self.beat_in_bar() == 0 {
+            self.bar_this_frame = true;
+            self.events.emit(Event::Downbeat { bar: self.bar });
+        }
+        self.events.emit(Event::Beat {
+            beat: self.beat,
+            beat_in_bar: self.beat_in_bar(),
+        });
+    }
+
+    fn advance_clock(&mut self, dt: f64) {
+        self.beat_this_frame = false;
+        self.bar_this_frame = false;
+
+        let bpm = match self.bpm {
+            Some(bpm) => bpm,
+            None => return,
+        };
+
+        let beats = dt * bpm / 60.0 * (1.0 + self.bend);
+        let correction = self.phase_error * (dt * PHASE_CORRECTION_RATE).min(1.0);
+        // the clock can be held back but never runs backwards
+        let step = (beats + correction).max(0.0);
+        self.phase_error -= step - beats;
+        self.phase += step;
+
+        if let Some(ramp) = &mut self.ramp {
+            self.bpm = Some(ramp.advance(step));
+            if ramp.is_done() {
+                self.ramp = None;
+            }
+        }
+
+        while self.phase >= 1.0 {
+            self.phase -= 1.0;
+            self.beat += 1;
+            self.beat_this_frame = true;
+            if self.beat_in_bar() == 0 {
+                self.bar = self.bar_at(self.beat);
+                self.bar_this_frame = true;
+                self.events.emit(Event::Downbeat { bar: self.bar });
+            }
+            self.events.emit(Event::Beat {
+                beat: self.beat,
+                beat_in_bar: self.beat_in_bar(),
+            });
+        }
     }
 
     pub fn update(&mut self) {
-        self.spring.update(0.05);
+        let current_time = self.clock.now();
+        let dt = self
+            .last_update
+            .map_or(0.0, |t| current_time.duration_since(t).as_secs_f64());
+        self.last_update = Some(current_time);
+        self.advance_clock(dt);
+        self.follow_grid(current_time);
+        self.report_tempo();
+
+        if self.state == TapTempoState::Inactive {
+            return;
+        }
+
+        self.spring.update(dt as f32);
         if let Some(last_tap) = self.taps.last() {
-            let current_time = Instant::now();
             self.seconds_since_last_tap =
                 Some(current_time.duration_since(*last_tap).as_secs_f64());
 
-            let average_interval = self.calculate_average_interval();
+            let interval = self.calculate_estimate().map(|e| e.interval);
 
-            if let Some(i) = average_interval {
+            if let Some(i) = interval {
                 if self.seconds_since_last_tap >= Some(i*2.0) {
                     self.timeout();
                 }
@@ -85,32 +504,127 @@ impl Model {
         }
     }
 
-    fn set_initial_time(&mut self) {
+    // one place to notice the tempo changing, however it changed
+    fn report_tempo(&mut self) {
+        let bpm = self.target_bpm();
+        let changed = match (bpm, self.reported_bpm) {
+            (Some(bpm), Some(reported)) => (bpm - reported).abs() >= TEMPO_EVENT_RESOLUTION,
+            (bpm, reported) => bpm.is_some() != reported.is_some(),
+        };
+        if changed {
+            self.reported_bpm = bpm;
+            self.events.emit(Event::TempoChanged { bpm });
+            if let (Some(bpm), Some(suggestion)) = (bpm, self.tempo_suggestion()) {
+                self.events.emit(Event::TempoOutOfRange { bpm, suggestion });
+            }
+        }
+    }
+
+    /// What's happened since the last drain. Call once a frame, after `update`.
+    pub fn drain_events(&mut self) -> impl Iterator<Item = Event> + '_ {
+        self.events.drain()
+    }
+
+    /// A channel that gets every event from now on, for things that live on other threads or
+    /// just want their own copy. One that falls too far behind misses events until it catches
+    /// up.
+    pub fn subscribe(&mut self) -> std::sync::mpsc::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    fn set_initial_time(&mut self, time: Instant) {
         self.state = TapTempoState::InitialTap;
         self.taps.clear();
-        println!("{:?}", self.state);
-        self.taps.push(Instant::now());
+        self.taps.push(time);
+        self.events.emit(Event::TapRegistered { time, taps: 1 });
     }
 
-    fn set_additional_time(&mut self) {
-        self.taps.push(Instant::now());
-        println!("tap");
+    fn set_additional_time(&mut self, time: Instant) {
+        // events can be handled out of order, but taps have to stay sorted
+        let time = self.taps.last().map_or(time, |last| time.max(*last));
+        self.taps.push(time);
+        if self.taps.len() > TAP_WINDOW {
+            self.taps.remove(0);
+        }
+        self.events.emit(Event::TapRegistered {
+            time,
+            taps: self.taps.len(),
+        });
     }
 
     pub fn tap(&mut self) {
+        self.tap_at(self.clock.now());
+    }
+
+    /// Start measuring `latency`: tap along to the flash `view` draws, then
+    /// `finish_calibration`. Without a tempo the flash comes at 120bpm until calibration ends.
+    pub fn start_calibration(&mut self) {
+        if self.bpm.is_none() {
+            // flashing from a beat right now
+            self.bpm = Some(CALIBRATION_BPM);
+            self.calibration_bpm = true;
+            self.phase = 0.0;
+            self.phase_error = 0.0;
+            self.last_update = Some(self.clock.now());
+        }
+        self.calibration = Some(Calibration::default());
+    }
+
+    pub fn is_calibrating(&self) -> bool {
+        self.calibration.is_some()
+    }
+
+    /// Stop calibrating and add what the taps measured to `latency`, returning the new value.
+    /// Too few taps leave it as it was and return `None`.
+    pub fn finish_calibration(&mut self) -> Option<f64> {
+        let calibration = self.calibration.take()?;
+        if std::mem::take(&mut self.calibration_bpm) {
+            self.bpm = None;
+            self.ramp = None;
+        }
+        // the flash was already running `latency` early, so the taps only measure what's left
+        let measured = calibration.latency()?;
+        self.latency += measured;
+        self.events.emit(Event::Calibrated {
+            latency: self.latency,
+        });
+        Some(self.latency)
+    }
+
+    // how far from the nearest beat being heard a calibration tap landed
+    fn calibration_offset(&self, time: Instant) -> f64 {
+        let bpm = self.bpm.unwrap_or(CALIBRATION_BPM);
+        let beats = self.beats_at(time);
+        (beats - beats.round()) * 60.0 / bpm
+    }
+
+    /// Register a tap that happened at `time`, e.g. when an input event was emitted rather than
+    /// when it got handled.
+    pub fn tap_at(&mut self, time: Instant) {
+        if self.calibration.is_some() {
+            let offset = self.calibration_offset(time);
+            if let Some(calibration) = &mut self.calibration {
+                calibration.add(offset);
+            }
+            return;
+        }
+        self.grid = None;
         match self.state {
-            TapTempoState::Inactive => self.set_initial_time(),
+            TapTempoState::Inactive => self.set_initial_time(time),
+            // two taps are only an interval, so the tempo waits for a third
             TapTempoState::InitialTap => {
                 self.state = TapTempoState::RecordingTaps;
                 self.spring.value = 0.5;
-                self.set_additional_time();
+                self.set_additional_time(time);
             }
             TapTempoState::RecordingTaps => {
                 self.spring.value = 0.5;
-                self.set_additional_time();
+                self.set_additional_time(time);
 
-                if let Some(bpm) = self.calculate_bpm() {
-                    self.set_bpm(bpm);
+                if let Some(estimate) = self.calculate_estimate() {
+                    self.confidence = Some(estimate.confidence);
+                    self.jitter = Some(estimate.jitter);
+                    self.set_bpm(self.tap_unit.to_quarter_bpm(estimate.bpm()));
                 }
             }
         }
@@ -120,20 +634,27 @@ impl Model {
         self.taps.clear();
         self.state = TapTempoState::Inactive;
         self.bpm = None;
-        println!("{:?}", self.state);
+        self.ramp = None;
+        self.confidence = None;
+        self.jitter = None;
+        self.phase = 0.0;
+        self.beat = 0;
+        self.bar = 0;
+        self.downbeat = 0;
+        self.phase_error = 0.0;
+        self.bend = 0.0;
+        self.grid = None;
+        self.calibration = None;
+        self.calibration_bpm = false;
+        self.events.emit(Event::Cleared);
     }
 }
 
-pub fn update(_app: &App, model: &mut Model, _update: Update) {
-    match model.state {
-        TapTempoState::InitialTap => model.update(),
-        TapTempoState::RecordingTaps => model.update(),
-        _ => {}
-    }
+pub fn update<C: Clock>(_app: &App, model: &mut Model<C>, _update: Update) {
+    model.update();
 }
 
-pub fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+pub fn view<C: Clock>(app: &App, model: &Model<C>, draw: &Draw) {
     let boundary = app.window_rect();
     let x = boundary.left() + 30.0;
     let y = boundary.top() - 30.0;
@@ -167,15 +688,46 @@ pub fn view(app: &App, model: &Model, frame: Frame) {
                 .rgb(1.0, 0.0, 0.0)
                 .radius(radius * scale)
                 .x_y(x, y);
+
+            // steadiness bar, full width when the taps are dead on
+            if let Some(confidence) = model.confidence {
+                let width = size * confidence as f32;
+                draw.rect()
+                    .rgba(1.0, 1.0, 1.0, 0.6)
+                    .x_y(x - size / 2.0 + width / 2.0, y - size / 2.0 - 4.0)
+                    .w_h(width, 2.0);
+            }
         }
         _ => {}
     }
-    draw.to_frame(app, &frame).unwrap();
+
+    // a beat to tap along to, flashing where the picture says the beat is
+    if model.is_calibrating() {
+        let flash = (1.0 - model.visual_beats().rem_euclid(1.0) * 4.0).max(0.0);
+        draw.ellipse()
+            .rgba(1.0, 1.0, 1.0, flash as f32)
+            .radius(40.0)
+            .x_y(0.0, 0.0);
+    }
+
+    // where we are in the bar, one dot per beat with the "one" a little bigger
+    if model.bpm.is_some() {
+        let current = model.beat_in_bar_at(model.visual_beats().max(0.0) as u64);
+        for i in 0..model.meter.beats_per_bar {
+            let alpha = if i == current { 1.0 } else { 0.3 };
+            let dot_radius = if i == 0 { 3.0 } else { 2.0 };
+            draw.ellipse()
+                .rgba(1.0, 1.0, 1.0, alpha)
+                .radius(dot_radius)
+                .x_y(x - size / 2.0 + 3.0 + i as f32 * 9.0, y - size / 2.0 - 12.0);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use clock::ManualClock;
 
     #[test]
     fn starts_with_no_tempo() {
@@ -199,26 +751,559 @@ mod tests {
         assert_eq!(model.state, TapTempoState::RecordingTaps);
     }
 
+    fn manual_model() -> Model<ManualClock> {
+        Model::with_clock(ManualClock::new())
+    }
+
+    fn tap_steadily(model: &mut Model<ManualClock>, taps: usize, interval: f64) {
+        for _ in 0..taps {
+            model.tap();
+            model.clock.advance_secs(interval);
+            model.update();
+        }
+    }
+
     #[test]
     fn calculates_bpm_correctly() {
-        // BEWARE: this test has a delay in it
-        //
-        // this method is not exact, so we test to make sure the difference between the expected
-        // value and actual value is small
+        // 500ms between taps should be 120bpm, from the third tap on
+        let mut model = manual_model();
+        model.tap();
+        model.clock.advance_secs(0.5);
+        model.tap();
+        assert_eq!(model.bpm, None);
+        model.clock.advance_secs(0.5);
+        model.tap();
+        model.clock.advance_secs(0.6);
+        model.update();
 
-        // 500ms between taps should be 120bpm
-        let expected_bpm = 120.0;
+        assert!((model.bpm.unwrap() - 120.0).abs() < 1e-6);
+    }
 
-        let mut model = Model::default();
+    #[test]
+    fn taps_keep_their_event_time() {
+        // taps handled together in one late frame still count from when they happened
+        let mut model = manual_model();
+        let start = model.clock.now();
+        model.clock.advance_secs(1.2);
+        for i in 0..3 {
+            model.tap_at(start + std::time::Duration::from_millis(i * 500));
+        }
+        model.update();
+
+        assert!((model.bpm.unwrap() - 120.0).abs() < 1e-6);
+        assert_eq!(model.state, TapTempoState::RecordingTaps);
+    }
+
+    #[test]
+    fn out_of_order_taps_do_not_go_back_in_time() {
+        let mut model = manual_model();
+        let start = model.clock.now();
+        model.tap_at(start + std::time::Duration::from_millis(500));
+        model.tap_at(start);
+
+        assert_eq!(model.taps[0], model.taps[1]);
+        assert_eq!(model.bpm, None);
+    }
+
+    #[test]
+    fn recording_times_out_after_two_missed_beats() {
+        let mut model = manual_model();
+        tap_steadily(&mut model, 4, 0.5);
+        assert_eq!(model.state, TapTempoState::RecordingTaps);
+
+        model.clock.advance_secs(0.4);
+        model.update();
+        assert_eq!(model.state, TapTempoState::RecordingTaps);
+
+        model.clock.advance_secs(0.2);
+        model.update();
+        assert_eq!(model.state, TapTempoState::Inactive);
+        assert!(model.bpm.is_some());
+    }
+
+    #[test]
+    fn initial_tap_times_out_after_cutoff() {
+        let mut model = manual_model();
         model.tap();
-        std::thread::sleep(std::time::Duration::from_millis(500));
+
+        model.clock.advance_secs(WAIT_CUTOFF - 0.1);
+        model.update();
+        assert_eq!(model.state, TapTempoState::InitialTap);
+
+        model.clock.advance_secs(0.2);
+        model.update();
+        assert_eq!(model.state, TapTempoState::Inactive);
+        assert_eq!(model.bpm, None);
+    }
+
+    #[test]
+    fn long_sessions_follow_the_latest_taps() {
+        let mut model = manual_model();
+        tap_steadily(&mut model, 1000, 0.5);
+        assert_eq!(model.taps.len(), TAP_WINDOW);
+        assert!((model.bpm.unwrap() - 120.0).abs() < 1e-6);
+
+        tap_steadily(&mut model, TAP_WINDOW, 0.4);
+        assert!((model.target_bpm().unwrap() - 150.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn tapped_tempo_drives_the_beat_clock() {
+        let mut model = manual_model();
+        tap_steadily(&mut model, 3, 0.5);
+        let beats = model.beats();
+
+        for _ in 0..120 {
+            model.clock.advance_secs(1.0 / 60.0);
+            model.update();
+        }
+        assert!((model.beats() - beats - 4.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn beat_clock_counts_beats_and_bars() {
+        let mut model = Model {
+            bpm: Some(120.0),
+            ..Default::default()
+        };
+
+        // 120bpm is two beats a second
+        model.advance_clock(0.25);
+        assert!((model.phase - 0.5).abs() < 1e-9);
+        assert!(!model.beat_this_frame);
+
+        model.advance_clock(0.25);
+        assert_eq!(model.beat, 1);
+        assert!(model.beat_this_frame);
+
+        model.advance_clock(1.5);
+        assert_eq!(model.beat, 4);
+        assert_eq!(model.bar, 1);
+        assert!(model.bar_this_frame);
+
+        model.advance_clock(0.1);
+        assert!(!model.beat_this_frame);
+        assert!(!model.bar_this_frame);
+    }
+
+    #[test]
+    fn tapping_nudges_phase_instead_of_resetting_it() {
+        let mut model = Model {
+            bpm: Some(120.0),
+            ..Default::default()
+        };
+        model.advance_clock(0.45);
+        let beats = model.beats();
+
+        // a slightly early tap pulls the clock forward over a few frames
+        model.phase_error = 0.1;
+        model.advance_clock(0.0);
+        assert_eq!(model.beats(), beats);
+        for _ in 0..60 {
+            model.advance_clock(1.0 / 60.0);
+        }
+        let expected = beats + 2.0 + 0.1;
+        assert!((model.beats() - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn sync_steers_small_errors_and_jumps_big_ones() {
+        let mut model = manual_model();
+        model.sync(120.0, 8.25);
+        assert_eq!(model.beat, 8);
+        assert_eq!(model.bar, 2);
+        assert!((model.phase - 0.25).abs() < 1e-9);
+
+        model.sync(120.0, 8.35);
+        assert_eq!(model.beat, 8);
+        assert!((model.phase - 0.25).abs() < 1e-9);
+        assert!((model.phase_error - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn follows_a_loaded_beat_grid() {
+        let mut model = manual_model();
+        let grid = BeatGrid {
+            bpm: 120.0,
+            downbeat: 0.5,
+            beats: (0..16).map(|i| i as f64 * 0.5).collect(),
+        };
+        model.load_grid(grid, model.clock.now());
+
+        for _ in 0..150 {
+            model.clock.advance_secs(1.0 / 60.0);
+            model.update();
+        }
+        // 2.5 seconds in is five beats, one of them before the downbeat
+        assert_eq!(model.bpm, Some(120.0));
+        assert!((model.beats() - 4.0).abs() < 0.01);
+        assert_eq!(model.bar, 1);
+
+        model.clear();
+        assert!(model.grid.is_none());
+    }
+
+    #[test]
+    fn taking_over_by_hand_drops_the_grid() {
+        let mut model = manual_model();
+        let grid = BeatGrid {
+            bpm: 120.0,
+            downbeat: 0.0,
+            beats: (0..16).map(|i| i as f64 * 0.5).collect(),
+        };
+        model.load_grid(grid.clone(), model.clock.now());
+        model.clock.advance_secs(1.0);
+        model.update();
+
+        model.nudge_bpm(1.0);
+        model.clock.advance_secs(0.5);
+        model.update();
+        assert!(model.grid.is_none());
+        assert_eq!(model.bpm, Some(121.0));
+        assert!((model.beats() - 3.0).abs() < 0.01);
+
+        model.load_grid(grid, model.clock.now());
+        model.update();
         model.tap();
-        std::thread::sleep(std::time::Duration::from_millis(1100));
+        assert!(model.grid.is_none());
+    }
+
+    #[test]
+    fn counts_bars_in_other_meters() {
+        let mut model = Model {
+            bpm: Some(120.0),
+            ..Default::default()
+        };
+        model.set_meter(Meter::SEVEN_FOUR);
+
+        model.advance_clock(3.0);
+        assert_eq!(model.beat, 6);
+        assert_eq!(model.bar, 0);
+        assert_eq!(model.beat_in_bar(), 6);
+        assert_eq!(model.next_bar(), 7.0);
+
+        model.advance_clock(0.5);
+        assert_eq!(model.bar, 1);
+        assert!(model.bar_this_frame);
+        assert_eq!(model.beat_in_bar(), 0);
+        assert_eq!(model.next_bar(), 14.0);
+    }
+
+    #[test]
+    fn the_one_can_be_tapped_in() {
+        let mut model = manual_model();
+        model.sync(120.0, 5.2);
+        model.update();
+        assert_eq!(model.beat_in_bar(), 1);
+
+        // a little early for the next beat still counts as it
+        model.clock.advance_secs(0.3);
+        model.set_downbeat();
+        assert!((model.beats() - 5.2).abs() < 1e-9);
+        assert_eq!(model.beat_in_bar(), 3);
+
+        model.clock.advance_secs(0.2);
         model.update();
+        assert_eq!(model.beat, 6);
+        assert!(model.bar_this_frame);
+        assert_eq!(model.beat_in_bar(), 0);
+
+        model.set_meter(Meter::THREE_FOUR);
+        assert_eq!(model.beat_in_bar(), 0);
+    }
+
+    #[test]
+    fn new_tempos_ramp_in_without_skipping() {
+        let mut model = manual_model();
+        model.tap();
+        model.clock.advance_secs(0.5);
+        model.tap();
+        model.clock.advance_secs(0.5);
+        model.tap();
+        assert_eq!(model.bpm, Some(120.0));
+
+        // a tap a little early starts a glide towards a faster tempo
+        model.clock.advance_secs(0.4);
+        model.update();
+        model.tap();
+        let target = model.target_bpm().unwrap();
+        assert!(target > 120.0);
+        assert_eq!(model.bpm, Some(120.0));
+
+        let mut last = model.beats();
+        let mut last_bpm = 120.0;
+        for _ in 0..60 {
+            model.clock.advance_secs(1.0 / 60.0);
+            model.update();
+            let bpm = model.bpm.unwrap();
+            assert!(bpm >= last_bpm && bpm <= target);
+            assert!(model.beats() > last && model.beats() - last < 0.1);
+            last = model.beats();
+            last_bpm = bpm;
+        }
+        assert_eq!(model.bpm, Some(target));
+    }
+
+    #[test]
+    fn nudging_the_tempo_keeps_the_beat() {
+        let mut model = Model {
+            bpm: Some(120.0),
+            ..Default::default()
+        };
+        model.advance_clock(0.8);
+        let beats = model.beats();
+
+        model.nudge_bpm(0.1);
+        model.nudge_bpm(0.1);
+        assert!((model.bpm.unwrap() - 120.2).abs() < 1e-9);
+        assert_eq!(model.beats(), beats);
+
+        model.nudge_bpm(-1000.0);
+        assert_eq!(model.bpm, Some(MIN_BPM));
+    }
+
+    #[test]
+    fn bending_pushes_the_phase_while_held() {
+        let mut model = Model {
+            bpm: Some(120.0),
+            ..Default::default()
+        };
+        model.set_bend(0.1);
+        model.advance_clock(1.0);
+        assert!((model.beats() - 2.2).abs() < 1e-9);
+
+        model.set_bend(0.0);
+        model.advance_clock(1.0);
+        assert!((model.beats() - 4.2).abs() < 1e-9);
+        assert_eq!(model.bpm, Some(120.0));
+    }
+
+    #[test]
+    fn resync_puts_the_one_on_the_tap() {
+        let mut model = manual_model();
+        model.sync(120.0, 9.3);
+        model.update();
+
+        model.resync();
+        assert!((model.beats() - 9.0).abs() < 1e-9);
+        assert_eq!(model.beat_in_bar(), 0);
+        assert_eq!(model.bpm, Some(120.0));
+
+        // most of a beat on, so this is an early tap for the next one
+        model.clock.advance_secs(0.3);
+        model.resync();
+        model.update();
+        assert!((model.beats() - 10.0).abs() < 1e-9);
+        assert_eq!(model.beat_in_bar(), 0);
+    }
+
+    #[test]
+    fn reports_what_happens_as_events() {
+        let mut model = manual_model();
+        let subscriber = model.subscribe();
+        tap_steadily(&mut model, 3, 0.5);
+
+        let events: Vec<Event> = model.drain_events().collect();
+        let taps = events
+            .iter()
+            .filter(|e| matches!(e, Event::TapRegistered { .. }))
+            .count();
+        assert_eq!(taps, 3);
+        assert!(events.contains(&Event::TempoChanged { bpm: Some(120.0) }));
+        assert!(events.contains(&Event::Beat {
+            beat: 1,
+            beat_in_bar: 1
+        }));
+
+        // two missed beats, then on round to the next bar
+        for _ in 0..3 {
+            model.clock.advance_secs(0.5);
+            model.update();
+        }
+        model.clear();
+        let events: Vec<Event> = model.drain_events().collect();
+        assert!(events.contains(&Event::TimedOut));
+        assert!(events.contains(&Event::Downbeat { bar: 1 }));
+        assert_eq!(events.last(), Some(&Event::Cleared));
+
+        model.update();
+        assert_eq!(
+            model.drain_events().collect::<Vec<_>>(),
+            vec![Event::TempoChanged { bpm: None }]
+        );
+        assert!(subscriber.try_iter().count() > 5);
+    }
+
+    #[test]
+    fn jumps_count_the_beat_they_land_on() {
+        let mut model = manual_model();
+        model.sync(120.0, 2.5);
+        model.drain_events().for_each(drop);
+
+        // skipping ahead by song position is one beat and one bar, not everything in between
+        model.sync(120.0, 12.25);
+        assert_eq!(
+            model.drain_events().collect::<Vec<_>>(),
+            vec![
+                Event::Downbeat { bar: 3 },
+                Event::Beat {
+                    beat: 12,
+                    beat_in_bar: 0
+                }
+            ]
+        );
+        assert!(model.beat_this_frame && model.bar_this_frame);
+
+        model.sync(120.0, 13.75);
+        assert_eq!(
+            model.drain_events().collect::<Vec<_>>(),
+            vec![Event::Beat {
+                beat: 13,
+                beat_in_bar: 1
+            }]
+        );
+
+        // a resync lands right on the one
+        model.update();
+        model.resync();
+        let events: Vec<Event> = model.drain_events().collect();
+        assert!(events.contains(&Event::Downbeat { bar: 0 }));
+        assert!(events.contains(&Event::Beat {
+            beat: 14,
+            beat_in_bar: 0
+        }));
+    }
+
+    #[test]
+    fn taps_can_be_other_note_values() {
+        let mut model = manual_model();
+        model.tap_unit = TapUnit::Half;
+        tap_steadily(&mut model, 3, 1.0);
+        assert!((model.bpm.unwrap() - 120.0).abs() < 1e-6);
+
+        // an eighth note tap lands halfway through a beat, which is right on time
+        let mut model = manual_model();
+        model.tap_unit = TapUnit::Eighth;
+        tap_steadily(&mut model, 3, 0.25);
+        assert!((model.bpm.unwrap() - 120.0).abs() < 1e-6);
+        assert!(model.phase_error.abs() < 1e-6);
+    }
+
+    #[test]
+    fn suggests_half_or_double_time_outside_the_range() {
+        let mut model = manual_model();
+        model.sync(170.0, 0.0);
+        assert_eq!(model.tempo_suggestion(), Some(85.0));
+
+        model.sync(35.0, 0.0);
+        assert_eq!(model.tempo_suggestion(), Some(140.0));
+        model.update();
+        assert!(model
+            .drain_events()
+            .any(|e| e == Event::TempoOutOfRange { bpm: 35.0, suggestion: 140.0 }));
+
+        model.accept_tempo_suggestion();
+        assert_eq!(model.bpm, Some(140.0));
+        assert_eq!(model.tempo_suggestion(), None);
+
+        model.set_tempo_range(100.0, 120.0).unwrap();
+        model.sync(90.0, 0.0);
+        assert_eq!(model.tempo_suggestion(), None);
+
+        for (low, high) in [(0.0, 120.0), (-80.0, -40.0), (160.0, 80.0), (80.0, f64::NAN)] {
+            assert!(model.set_tempo_range(low, high).is_err());
+        }
+        assert_eq!(model.tempo_range(), (100.0, 120.0));
+
+        model.sync(0.0, 0.0);
+        assert_eq!(model.tempo_suggestion(), None);
+    }
+
+    #[test]
+    fn grooves_count_from_the_one() {
+        let mut model = manual_model();
+        model.groove = Groove::swung(groove::Subdivision::Eighth, 75.0);
+        model.sync(120.0, 3.75);
+        assert!((model.grooved_beats() - 3.5).abs() < 1e-9);
+        assert!(model.swung_phase().abs() < 1e-9);
+
+        // the second beat of the bar drags behind
+        let mut drag = vec![0.0; 8];
+        drag[2] = 0.2;
+        model.groove = Groove::straight(groove::Subdivision::Eighth).with_template(drag);
+        model.jump_to(1.1);
+        assert!((model.grooved_beats() - 1.0).abs() < 1e-9);
+
+        // which is a beat later once the one is moved
+        model.jump_to(1.0);
+        model.set_downbeat();
+        model.jump_to(1.1);
+        assert!((model.grooved_beats() - 1.1).abs() < 1e-9);
+        model.jump_to(2.1);
+        assert!((model.grooved_beats() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn calibration_measures_how_late_the_picture_is() {
+        let mut model = manual_model();
+        model.start_calibration();
+        assert_eq!(model.bpm, Some(CALIBRATION_BPM));
+
+        // seeing the flash 30ms after the beat is heard
+        model.clock.advance_secs(0.03);
+        tap_steadily(&mut model, 8, 0.5);
+        assert_eq!(model.state, TapTempoState::Inactive);
+        assert!((model.finish_calibration().unwrap() - 0.03).abs() < 1e-9);
+        assert!(!model.is_calibrating());
+        assert!(matches!(model.drain_events().last(), Some(Event::Calibrated { .. })));
+
+        // once it's made up for, tapping with the flash is tapping on the beat
+        model.start_calibration();
+        assert!((model.visual_beats() - model.beats() - 0.06).abs() < 1e-9);
+        tap_steadily(&mut model, 8, 0.5);
+        assert!((model.finish_calibration().unwrap() - 0.03).abs() < 1e-9);
+
+        // and too few taps don't change anything
+        model.start_calibration();
+        model.tap();
+        assert_eq!(model.finish_calibration(), None);
+        assert!((model.latency - 0.03).abs() < 1e-9);
+
+        // the flash's tempo goes again once it's done with, either way
+        assert_eq!(model.bpm, None);
+        model.update();
+        assert!(model
+            .drain_events()
+            .any(|e| e == Event::TempoChanged { bpm: None }));
+
+        // but a tempo that was there already stays
+        model.sync(100.0, 0.0);
+        model.start_calibration();
+        model.finish_calibration();
+        assert_eq!(model.bpm, Some(100.0));
+    }
+
+    #[test]
+    fn latency_only_moves_what_gets_drawn() {
+        let mut model = Model {
+            bpm: Some(120.0),
+            latency: 0.1,
+            ..Default::default()
+        };
+        model.advance_clock(1.9);
+
+        // the picture is already on the next bar...
+        assert!((model.visual_beats() - 4.0).abs() < 1e-9);
+        assert_eq!(model.visual_bar(), 1);
+        // ...while the beat as heard, and everything that goes out with it, isn't
+        assert_eq!(model.beat_in_bar(), 3);
+        assert!(!model.drain_events().any(|e| matches!(e, Event::Downbeat { .. })));
+        assert_eq!(model.next_bar(), 4.0);
+        assert_eq!(model.next_visual_bar(), 8.0);
 
-        let actual_bpm = model.bpm;
-        let diff = (expected_bpm - actual_bpm.unwrap()).abs();
-        assert!(diff < 1.0);
+        model.advance_clock(0.15);
+        assert!(model.bar_this_frame);
+        assert!(model.drain_events().any(|e| e == Event::Downbeat { bar: 1 }));
     }
 
     #[test]
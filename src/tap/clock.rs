@@ -0,0 +1,50 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when it's told to, so tempo tests don't have to sleep.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Cell<Instant>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock {
+            now: Cell::new(Instant::now()),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+
+    pub fn advance_secs(&self, seconds: f64) {
+        self.advance(Duration::from_secs_f64(seconds));
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
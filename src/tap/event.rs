@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::time::Instant;
+
+// events nobody drains are dropped, oldest first, past this many; subscribers that fall this
+// far behind miss the newest instead
+const MAX_QUEUED: usize = 256;
+
+/// Something the metronome did.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// `taps` is how many taps are counting towards the tempo so far.
+    TapRegistered { time: Instant, taps: usize },
+    /// The tempo being headed for changed, or went away with `None`.
+    TempoChanged { bpm: Option<f64> },
+    /// The new tempo is outside `tempo_range`, and probably meant to be `suggestion`.
+    TempoOutOfRange { bpm: f64, suggestion: f64 },
+    /// Tapping stopped without tapping a tempo in, or long enough after one to end the session.
+    TimedOut,
+    Cleared,
+    Beat { beat: u64, beat_in_bar: u64 },
+    /// The first beat of bar `bar`, which also gets a `Beat`.
+    Downbeat { bar: u64 },
+    /// Calibration finished, and the picture is now run `latency` seconds ahead.
+    Calibrated { latency: f64 },
+}
+
+/// A queue to drain once a frame, plus channels for anything that wants its own copy.
+#[derive(Debug, Default)]
+pub struct Events {
+    queue: VecDeque<Event>,
+    subscribers: Vec<SyncSender<Event>>,
+}
+
+impl Events {
+    pub fn emit(&mut self, event: Event) {
+        // hung-up receivers stop getting sent to
+        self.subscribers.retain(|subscriber| {
+            !matches!(
+                subscriber.try_send(event.clone()),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+
+        if self.queue.len() == MAX_QUEUED {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(event);
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item = Event> + '_ {
+        self.queue.drain(..)
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<Event> {
+        let (sender, receiver) = sync_channel(MAX_QUEUED);
+        self.subscribers.push(sender);
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscribers_and_the_queue_each_get_everything() {
+        let mut events = Events::default();
+        let receiver = events.subscribe();
+        events.emit(Event::Cleared);
+        events.emit(Event::TimedOut);
+
+        assert_eq!(events.drain().collect::<Vec<_>>(), vec![Event::Cleared, Event::TimedOut]);
+        assert_eq!(events.drain().count(), 0);
+        assert_eq!(receiver.try_iter().count(), 2);
+
+        drop(receiver);
+        events.emit(Event::Cleared);
+        assert!(events.subscribers.is_empty());
+    }
+
+    #[test]
+    fn an_undrained_queue_keeps_the_latest() {
+        let mut events = Events::default();
+        let subscriber = events.subscribe();
+        for beat in 0..1000 {
+            events.emit(Event::Beat {
+                beat,
+                beat_in_bar: beat % 4,
+            });
+        }
+        let queued: Vec<Event> = events.drain().collect();
+        assert_eq!(queued.len(), MAX_QUEUED);
+        // a subscriber that never reads holds on to what it had room for
+        assert_eq!(subscriber.try_iter().count(), MAX_QUEUED);
+        assert_eq!(events.subscribers.len(), 1);
+        assert_eq!(
+            queued.last(),
+            Some(&Event::Beat {
+                beat: 999,
+                beat_in_bar: 3
+            })
+        );
+    }
+}
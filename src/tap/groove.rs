@@ -0,0 +1,160 @@
+/// Which notes get swung.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Subdivision {
+    #[default]
+    Eighth,
+    Sixteenth,
+}
+
+impl Subdivision {
+    pub fn steps_per_beat(self) -> u64 {
+        match self {
+            Subdivision::Eighth => 2,
+            Subdivision::Sixteenth => 4,
+        }
+    }
+}
+
+/// Bends straight time into swung time, MPC style: every other step comes late by however
+/// much the swing percentage says. A template can push or pull individual steps on top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Groove {
+    pub subdivision: Subdivision,
+    /// how much of each pair of steps the first one gets: 50 is straight, 66 is a triplet shuffle
+    pub swing: f64,
+    // see `with_template`
+    template: Vec<f64>,
+}
+
+// how far a template can move a step, as a fraction of the step either side of it; any further
+// and steps could pass each other
+const MAX_OFFSET: f64 = 0.45;
+
+impl Default for Groove {
+    fn default() -> Groove {
+        Groove::straight(Subdivision::default())
+    }
+}
+
+impl Groove {
+    pub fn straight(subdivision: Subdivision) -> Groove {
+        Groove::swung(subdivision, 50.0)
+    }
+
+    pub fn swung(subdivision: Subdivision, swing: f64) -> Groove {
+        Groove {
+            subdivision,
+            swing,
+            template: Vec::new(),
+        }
+    }
+
+    /// Push or pull individual steps on top of the swing: one offset per step, repeating from
+    /// the "one", in steps with positive being late. Each is kept to under half a step either
+    /// way, so steps never pass each other; with swing on they're in the shorter swung step.
+    pub fn with_template(self, template: Vec<f64>) -> Groove {
+        let template = template
+            .into_iter()
+            .map(|offset| match offset.is_finite() {
+                true => offset.clamp(-MAX_OFFSET, MAX_OFFSET),
+                false => 0.0,
+            })
+            .collect();
+        Groove { template, ..self }
+    }
+
+    pub fn template(&self) -> &[f64] {
+        &self.template
+    }
+
+    // where step `k` starts in straight steps
+    fn step_start(&self, k: i64) -> f64 {
+        let swing = (self.swing / 100.0).clamp(0.01, 0.99);
+        let pair = k.div_euclid(2) as f64 * 2.0;
+        let swung = if k.rem_euclid(2) == 0 {
+            pair
+        } else {
+            pair + 2.0 * swing
+        };
+        let offset = match self.template.len() {
+            0 => 0.0,
+            n => self.template[k.rem_euclid(n as i64) as usize],
+        };
+        // every step sits between a long swung step and a short one; offsets are in short ones
+        let short_step = 2.0 * swing.min(1.0 - swing);
+        swung + offset * short_step
+    }
+
+    /// Turn straight beats (counted from the "one") into grooved ones: whole steps land where the
+    /// groove puts them, with the time in between stretched or squeezed to fit.
+    pub fn apply(&self, beats: f64) -> f64 {
+        let steps_per_beat = self.subdivision.steps_per_beat() as f64;
+        let straight = beats * steps_per_beat;
+
+        let mut k = straight.floor() as i64;
+        while self.step_start(k) > straight {
+            k -= 1;
+        }
+        while self.step_start(k + 1) <= straight {
+            k += 1;
+        }
+        let (start, end) = (self.step_start(k), self.step_start(k + 1));
+        let grooved = k as f64 + (straight - start) / (end - start);
+
+        grooved / steps_per_beat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn straight_is_left_alone() {
+        let groove = Groove::straight(Subdivision::Sixteenth);
+        for beats in [0.0, 0.3, 1.75, 13.9] {
+            assert!((groove.apply(beats) - beats).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn swing_delays_the_offbeat() {
+        // at 2/3 swing the offbeat eighth lands on the last triplet
+        let groove = Groove::swung(Subdivision::Eighth, 200.0 / 3.0);
+        assert!((groove.apply(1.0 / 3.0) - 0.25).abs() < 1e-9);
+        assert!((groove.apply(2.0 / 3.0) - 0.5).abs() < 1e-9);
+        assert!((groove.apply(0.75 + 4.0) - 4.625).abs() < 1e-9);
+        assert!((groove.apply(3.0) - 3.0).abs() < 1e-9);
+
+        let sixteenths = Groove::swung(Subdivision::Sixteenth, 60.0);
+        assert!((sixteenths.apply(0.3) - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn templates_push_steps_around() {
+        let groove = Groove::straight(Subdivision::Sixteenth).with_template(vec![0.0, 0.0, 0.2, 0.0]);
+        // the third sixteenth of every beat is a fifth of a step late
+        assert!((groove.apply(0.55) - 0.5).abs() < 1e-9);
+        assert!((groove.apply(2.55) - 2.5).abs() < 1e-9);
+        assert!((groove.apply(-0.45) + 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn extreme_templates_never_run_backwards() {
+        let template = vec![3.0, -3.0, f64::NAN, 0.9, -0.9, f64::INFINITY];
+        assert_eq!(
+            Groove::default().with_template(template.clone()).template(),
+            &[MAX_OFFSET, -MAX_OFFSET, 0.0, MAX_OFFSET, -MAX_OFFSET, 0.0]
+        );
+
+        for swing in [50.0, 75.0, 99.0] {
+            let groove = Groove::swung(Subdivision::Sixteenth, swing).with_template(template.clone());
+            let mut last = groove.apply(-2.0);
+            for i in 1..=800 {
+                let grooved = groove.apply(-2.0 + i as f64 * 0.01);
+                assert!(grooved.is_finite() && grooved > last, "{} at {}% swing", grooved, swing);
+                last = grooved;
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// fewer taps than this are too easy to fluff to be worth trusting
+const MIN_TAPS: usize = 4;
+
+/// Taps along to a flashing beat, each one recorded as how far (in seconds, positive is late) it
+/// landed from the beat being heard. Tapping to the flash means tapping when the picture shows
+/// the beat, so the typical offset is how far the picture trails the sound.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Calibration {
+    offsets: Vec<f64>,
+}
+
+impl Calibration {
+    pub fn add(&mut self, offset: f64) {
+        self.offsets.push(offset);
+    }
+
+    pub fn taps(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// The average offset, once there are enough taps. Only the middle half of them count, so
+    /// a stray tap doesn't drag it around.
+    pub fn latency(&self) -> Option<f64> {
+        if self.offsets.len() < MIN_TAPS {
+            return None;
+        }
+        let mut sorted = self.offsets.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let quarter = sorted.len() / 4;
+        let middle = &sorted[quarter..sorted.len() - quarter];
+        Some(middle.iter().sum::<f64>() / middle.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_the_middle_taps() {
+        let mut calibration = Calibration::default();
+        for offset in [0.04, 0.05, 0.06] {
+            calibration.add(offset);
+        }
+        assert_eq!(calibration.latency(), None);
+
+        // one wild tap either way doesn't count
+        calibration.add(0.05);
+        calibration.add(-0.3);
+        calibration.add(0.4);
+        assert_eq!(calibration.taps(), 6);
+        assert!((calibration.latency().unwrap() - 0.05).abs() < 1e-9);
+    }
+}
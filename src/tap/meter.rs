@@ -0,0 +1,67 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A time signature, always over 4: every beat the metronome counts is a quarter note, because
+/// that's what `bpm` and everything that follows the clock measures in. Something in 7/8 can be
+/// played as 7/4 with the eighths tapped in as quarters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Meter {
+    pub beats_per_bar: u64,
+}
+
+impl Meter {
+    pub const FOUR_FOUR: Meter = Meter::new(4);
+    pub const THREE_FOUR: Meter = Meter::new(3);
+    pub const SEVEN_FOUR: Meter = Meter::new(7);
+
+    pub const fn new(beats_per_bar: u64) -> Meter {
+        Meter {
+            // a bar always has at least one beat in it
+            beats_per_bar: if beats_per_bar == 0 { 1 } else { beats_per_bar },
+        }
+    }
+}
+
+impl Default for Meter {
+    fn default() -> Meter {
+        Meter::FOUR_FOUR
+    }
+}
+
+impl fmt::Display for Meter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/4", self.beats_per_bar)
+    }
+}
+
+/// Parses "7/4", or just "7". Anything over something other than 4 is an error rather than
+/// being counted in the wrong notes.
+impl FromStr for Meter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Meter, String> {
+        let (beats, note) = s.split_once('/').unwrap_or((s, "4"));
+        if note.trim() != "4" {
+            return Err(format!("{:?} isn't over 4; bars are counted in quarter notes", s));
+        }
+        match beats.trim().parse::<u64>() {
+            Ok(n) if n > 0 => Ok(Meter::new(n)),
+            _ => Err(format!("bad time signature {:?}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_time_signatures() {
+        assert_eq!("7/4".parse(), Ok(Meter::SEVEN_FOUR));
+        assert_eq!("3".parse(), Ok(Meter::THREE_FOUR));
+        assert_eq!(Meter::FOUR_FOUR.to_string(), "4/4");
+        assert!("7/8".parse::<Meter>().is_err());
+        assert!("0/4".parse::<Meter>().is_err());
+        assert!("four".parse::<Meter>().is_err());
+    }
+}
@@ -0,0 +1,63 @@
+/// A glide from one tempo to another, measured in beats so it feels the same at any tempo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ramp {
+    pub from: f64,
+    pub to: f64,
+    length: f64,
+    progress: f64,
+}
+
+impl Ramp {
+    pub fn new(from: f64, to: f64, length: f64) -> Self {
+        Ramp {
+            from,
+            to,
+            length,
+            progress: 0.0,
+        }
+    }
+
+    /// Move `beats` further along and return the tempo there.
+    pub fn advance(&mut self, beats: f64) -> f64 {
+        self.progress += beats;
+        self.bpm()
+    }
+
+    pub fn bpm(&self) -> f64 {
+        if self.is_done() {
+            return self.to;
+        }
+        // eases in and out, so there's no kink at either end
+        let t = self.progress / self.length;
+        let eased = t * t * (3.0 - 2.0 * t);
+        self.from + (self.to - self.from) * eased
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.progress >= self.length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glides_over_its_length() {
+        let mut ramp = Ramp::new(120.0, 140.0, 4.0);
+        assert_eq!(ramp.bpm(), 120.0);
+
+        assert!((ramp.advance(2.0) - 130.0).abs() < 1e-9);
+        assert!(!ramp.is_done());
+
+        assert_eq!(ramp.advance(3.0), 140.0);
+        assert!(ramp.is_done());
+    }
+
+    #[test]
+    fn zero_length_is_instant() {
+        let ramp = Ramp::new(120.0, 90.0, 0.0);
+        assert!(ramp.is_done());
+        assert_eq!(ramp.bpm(), 90.0);
+    }
+}
@@ -1,41 +1,295 @@
-pub struct Spring {
+use std::f32::consts::TAU;
+
+use nannou::color::LinSrgb;
+use nannou::geom::{Vec2, Vec3};
+
+use crate::tween::Tweenable;
+
+/// Anything a spring can pull around: anything that can be tweened, with a size and a zero.
+pub trait Springy: Tweenable + Default {
+    /// How big it is, for telling when the spring has settled.
+    fn magnitude(self) -> f32;
+}
+
+impl Springy for f32 {
+    fn magnitude(self) -> f32 {
+        self.abs()
+    }
+}
+
+impl Springy for Vec2 {
+    fn magnitude(self) -> f32 {
+        self.length()
+    }
+}
+
+impl Springy for Vec3 {
+    fn magnitude(self) -> f32 {
+        self.length()
+    }
+}
+
+// only linear colours, since blending gamma-encoded ones doesn't add up
+impl Springy for LinSrgb {
+    fn magnitude(self) -> f32 {
+        Vec3::new(self.red, self.green, self.blue).length()
+    }
+}
+
+// damping ratios this close to 1 count as critically damped, where the other two solutions
+// divide by nearly zero
+const CRITICAL_EPSILON: f32 = 1e-4;
+// `is_settled` for values around 0..1; anything in pixels wants `is_settled_within`
+const SETTLED_DISTANCE: f32 = 1e-3;
+const SETTLED_SPEED: f32 = 1e-3;
+
+pub struct Spring<T: Springy = f32> {
     pub k: f32,
     pub mass: f32,
     pub damping: f32,
-    pub value: f32,
-    pub target_value: f32,
-    pub velocity: f32,
+    pub value: T,
+    pub target_value: T,
+    pub velocity: T,
 }
 
-impl Spring {
-    pub fn new(k: f32, mass: f32, damping: f32, value: f32, target_value: f32) -> Self {
+impl<T: Springy> Spring<T> {
+    pub fn new(k: f32, mass: f32, damping: f32, value: T, target_value: T) -> Self {
         Spring {
             k,
             mass,
             damping,
             value,
             target_value,
-            velocity: 0.0,
+            velocity: T::default(),
         }
     }
 
-    // Method to calculate the force exerted by the spring
-    fn calculate_force(&self) -> f32 {
-        let displacement = self.value - self.target_value;
-        -self.k * displacement
+    /// A spring described by how it moves instead of its physics. `response` is roughly how
+    /// many seconds it takes to get there, and `damping_ratio` how much it overshoots: 1 glides
+    /// straight in, lower bounces (0.3 is wobbly), higher is more sluggish.
+    pub fn with_response(response: f32, damping_ratio: f32, value: T, target_value: T) -> Self {
+        let mut spring = Spring::new(0.0, 1.0, 0.0, value, target_value);
+        spring.set_response(response, damping_ratio);
+        spring
+    }
+
+    /// Like `with_response`, but with `bounce` from 0 (no overshoot) towards 1 (bounces forever).
+    /// Negative bounce is sluggish.
+    pub fn with_bounce(response: f32, bounce: f32, value: T, target_value: T) -> Self {
+        let damping_ratio = if bounce >= 0.0 {
+            1.0 - bounce
+        } else {
+            1.0 / (1.0 + bounce).max(f32::EPSILON)
+        };
+        Spring::with_response(response, damping_ratio, value, target_value)
+    }
+
+    /// Quick and clean, for UI and things that should just get there.
+    pub fn snappy(value: T) -> Self {
+        Spring::with_response(0.2, 1.0, value, value)
+    }
+
+    /// Slow and smooth, for drifting cameras and colours.
+    pub fn gentle(value: T) -> Self {
+        Spring::with_response(0.8, 1.0, value, value)
+    }
+
+    /// Overshoots and jiggles, for pulses and anything that should feel like it got hit.
+    pub fn wobbly(value: T) -> Self {
+        Spring::with_response(0.4, 0.3, value, value)
+    }
+
+    /// Change how the spring feels, carrying on from where it is and how fast it's moving.
+    pub fn set_response(&mut self, response: f32, damping_ratio: f32) {
+        let omega = TAU / response.max(f32::EPSILON);
+        self.k = omega * omega * self.mass;
+        self.damping = 2.0 * damping_ratio * omega * self.mass;
+    }
+
+    pub fn response(&self) -> f32 {
+        TAU / (self.k / self.mass).sqrt()
+    }
+
+    pub fn damping_ratio(&self) -> f32 {
+        self.damping / (2.0 * (self.k * self.mass).sqrt())
+    }
+
+    /// Head somewhere else. The velocity carries over, so there's no jolt even mid-flight.
+    pub fn retarget(&mut self, target_value: T) {
+        self.target_value = target_value;
     }
 
-    // Method to calculate the acceleration of the mass
-    fn calculate_acceleration(&self) -> f32 {
-        let force = self.calculate_force();
-        // F = ma, so a = F / m
-        (force - self.damping * self.velocity) / self.mass
+    /// Close enough to the target and slow enough that nobody will see it move again, for
+    /// values around 0..1.
+    pub fn is_settled(&self) -> bool {
+        self.is_settled_within(SETTLED_DISTANCE, SETTLED_SPEED)
     }
 
-    // Method to update the spring system over a small time step
+    /// `is_settled` with thresholds in the value's own units (pixels, say), `speed` per second.
+    pub fn is_settled_within(&self, distance: f32, speed: f32) -> bool {
+        (self.value - self.target_value).magnitude() <= distance
+            && self.velocity.magnitude() <= speed
+    }
+
+    /// Move `dt` seconds along. This solves the spring exactly rather than stepping it, so the
+    /// motion is the same however the time is chopped up: pass the real frame time
+    /// (`Update::since_last`) and it looks the same at any frame rate.
     pub fn update(&mut self, dt: f32) {
-        let acceleration = self.calculate_acceleration();
-        self.velocity += acceleration * dt;
-        self.value += self.velocity * dt;
+        if dt <= 0.0 || self.mass <= 0.0 {
+            return;
+        }
+        let displacement = self.value - self.target_value;
+        let [[a, b], [c, d]] = self.solution(dt);
+        self.value = self.target_value + displacement * a + self.velocity * b;
+        self.velocity = displacement * c + self.velocity * d;
+    }
+
+    // the damped harmonic oscillator is linear, so after `t` seconds the displacement and
+    // velocity are fixed mixes of where they started:
+    //   displacement = a * displacement0 + b * velocity0
+    //   velocity     = c * displacement0 + d * velocity0
+    fn solution(&self, t: f32) -> [[f32; 2]; 2] {
+        let omega = (self.k.max(0.0) / self.mass).sqrt();
+        let decay = self.damping / self.mass;
+
+        // no spring at all, just drag
+        if omega == 0.0 {
+            let e = (-decay * t).exp();
+            let drift = if decay > 0.0 { (1.0 - e) / decay } else { t };
+            return [[1.0, drift], [0.0, e]];
+        }
+
+        let zeta = decay / (2.0 * omega);
+        if (zeta - 1.0).abs() < CRITICAL_EPSILON {
+            let e = (-omega * t).exp();
+            [
+                [e * (1.0 + omega * t), e * t],
+                [-e * omega * omega * t, e * (1.0 - omega * t)],
+            ]
+        } else if zeta < 1.0 {
+            // bouncy
+            let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+            let e = (-zeta * omega * t).exp();
+            let (sin, cos) = (omega_d * t).sin_cos();
+            [
+                [e * (cos + zeta * omega / omega_d * sin), e * sin / omega_d],
+                [
+                    -e * omega * omega / omega_d * sin,
+                    e * (cos - zeta * omega / omega_d * sin),
+                ],
+            ]
+        } else {
+            // sluggish
+            let root = omega * (zeta * zeta - 1.0).sqrt();
+            let (r1, r2) = (-zeta * omega + root, -zeta * omega - root);
+            let (e1, e2) = ((r1 * t).exp(), (r2 * t).exp());
+            let span = r1 - r2;
+            [
+                [(r1 * e2 - r2 * e1) / span, (e1 - e2) / span],
+                [r1 * r2 * (e2 - e1) / span, (r1 * e1 - r2 * e2) / span],
+            ]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the same second of motion, chopped into frames
+    fn run<T: Springy>(spring: &mut Spring<T>, fps: u32) {
+        for _ in 0..fps {
+            spring.update(1.0 / fps as f32);
+        }
+    }
+
+    #[test]
+    fn looks_the_same_at_any_frame_rate() {
+        // bouncy, critical and sluggish
+        for damping in [0.9, 2.0 * 10.0f32.sqrt(), 20.0] {
+            let mut slow = Spring::new(10.0, 1.0, damping, Vec2::ZERO, Vec2::new(100.0, -50.0));
+            let mut fast = Spring::new(10.0, 1.0, damping, Vec2::ZERO, Vec2::new(100.0, -50.0));
+            run(&mut slow, 30);
+            run(&mut fast, 144);
+            assert!(slow.value.distance(fast.value) < 1e-3, "{}", damping);
+            assert!(slow.velocity.distance(fast.velocity) < 1e-3, "{}", damping);
+        }
+    }
+
+    #[test]
+    fn settles_on_the_target() {
+        let target = Vec3::new(1.0, 2.0, 3.0);
+        let mut spring = Spring::new(10.0, 1.0, 0.9, Vec3::ZERO, target);
+        for _ in 0..30 {
+            run(&mut spring, 60);
+        }
+        assert!(spring.value.distance(target) < 1e-3);
+
+        // a big frame is no less stable than lots of small ones
+        let mut scalar = Spring::new(10.0, 1.0, 0.9, 0.0, 1.0);
+        scalar.update(100.0);
+        assert!((scalar.value - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn described_by_how_it_moves() {
+        let spring = Spring::with_response(0.5, 0.3, 0.0, 1.0);
+        assert!((spring.response() - 0.5).abs() < 1e-5);
+        assert!((spring.damping_ratio() - 0.3).abs() < 1e-5);
+        assert!((Spring::with_bounce(0.5, 0.7, 0.0, 1.0).damping_ratio() - 0.3).abs() < 1e-5);
+        assert!(Spring::with_bounce(0.5, -0.5, 0.0, 1.0).damping_ratio() > 1.0);
+
+        // critically damped never overshoots
+        let mut snappy = Spring::snappy(0.0);
+        snappy.retarget(1.0);
+        for _ in 0..60 {
+            snappy.update(1.0 / 60.0);
+            assert!(snappy.value <= 1.0);
+        }
+        assert!(snappy.is_settled());
+
+        // but wobbly does
+        let mut wobbly = Spring::wobbly(0.0);
+        wobbly.retarget(1.0);
+        run(&mut wobbly, 60);
+        assert!(wobbly.value > 1.0 || wobbly.velocity < 0.0);
+        assert!(!wobbly.is_settled());
+    }
+
+    #[test]
+    fn retargeting_keeps_the_velocity() {
+        let h = 1e-4;
+        let mut spring = Spring::wobbly(Vec2::ZERO);
+        spring.retarget(Vec2::new(100.0, 0.0));
+        spring.update(0.1);
+        let before = spring.value;
+        spring.update(h);
+        let (at, velocity) = (spring.value, spring.velocity);
+
+        spring.retarget(Vec2::new(-100.0, 100.0));
+        spring.update(h);
+        let after = spring.value;
+
+        // it's really moving, and carries straight on through the retarget: the steps just
+        // before and after it go the same way at the same speed, near enough
+        assert!(velocity.length() > 100.0);
+        let (speed_in, speed_out) = ((at - before) / h, (after - at) / h);
+        assert!(speed_in.distance(velocity) < velocity.length() * 0.02);
+        assert!(speed_out.distance(velocity) < velocity.length() * 0.02);
+        assert!(spring.velocity.distance(velocity) < velocity.length() * 0.02);
+
+        // and only then turns towards the new target
+        spring.update(0.1);
+        assert!(spring.velocity.y > 0.0);
+        assert!(!spring.is_settled_within(0.5, 0.5));
+    }
+
+    #[test]
+    fn springs_colours() {
+        let white = LinSrgb::new(1.0, 1.0, 1.0);
+        let mut spring = Spring::new(10.0, 1.0, 5.0, LinSrgb::new(0.0, 0.0, 0.0), white);
+        run(&mut spring, 60);
+        assert!(spring.value.red > 0.5 && spring.value.red < 1.0);
+        assert_eq!(spring.value.red, spring.value.blue);
     }
 }
@@ -0,0 +1,168 @@
+// Tempo estimation from a run of tap times.
+//
+// Taps are fitted to a straight line (tap time against beat number), so every tap counts
+// instead of just the first and the last. Taps that land far from that line are dropped and
+// the line is fitted again, so one sloppy tap doesn't drag the tempo around.
+
+// taps further than this fraction of a beat from the fitted grid are treated as mistakes
+const OUTLIER_THRESHOLD: f64 = 0.2;
+// jitter, as a fraction of a beat, at which confidence reaches zero
+const MAX_JITTER: f64 = 0.1;
+// a couple of taps always fit perfectly, so confidence ramps up over this many intervals
+const CONFIDENT_INTERVALS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// seconds per beat
+    pub interval: f64,
+    /// RMS distance of the kept taps from the fitted beat grid, in seconds
+    pub jitter: f64,
+    /// how steady the taps were, 0..1
+    pub confidence: f64,
+    /// how many taps survived outlier rejection
+    pub taps_used: usize,
+}
+
+impl Estimate {
+    pub fn bpm(&self) -> f64 {
+        60.0 / self.interval
+    }
+}
+
+/// Estimate the beat interval from tap times in seconds, oldest first.
+pub fn estimate(times: &[f64]) -> Option<Estimate> {
+    if times.len() < 2 {
+        return None;
+    }
+
+    let mut intervals: Vec<f64> = times.windows(2).map(|w| w[1] - w[0]).collect();
+    let median_interval = median(&mut intervals);
+    if median_interval <= 0.0 {
+        return None;
+    }
+
+    // number each tap by the beat it belongs to, so a skipped tap doesn't halve the tempo
+    let points: Vec<(f64, f64)> = times
+        .iter()
+        .map(|t| (((t - times[0]) / median_interval).round(), *t))
+        .collect();
+
+    let (mut intercept, mut slope) = fit(&points)?;
+    let mut kept: Vec<(f64, f64)> = points
+        .iter()
+        .copied()
+        .filter(|(n, t)| (t - (intercept + slope * n)).abs() <= OUTLIER_THRESHOLD * slope)
+        .collect();
+
+    if kept.len() < 2 {
+        kept = points;
+    } else if kept.len() < points.len() {
+        if let Some(line) = fit(&kept) {
+            (intercept, slope) = line;
+        }
+    }
+
+    let squared_error = kept
+        .iter()
+        .map(|(n, t)| (t - (intercept + slope * n)).powi(2))
+        .sum::<f64>();
+    let jitter = (squared_error / kept.len() as f64).sqrt();
+
+    let steadiness = (1.0 - jitter / (MAX_JITTER * slope)).clamp(0.0, 1.0);
+    let experience = (kept.len() - 1).min(CONFIDENT_INTERVALS) as f64 / CONFIDENT_INTERVALS as f64;
+
+    Some(Estimate {
+        interval: slope,
+        jitter,
+        confidence: steadiness * experience,
+        taps_used: kept.len(),
+    })
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+// least-squares fit of time = intercept + slope * beat
+fn fit(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let count = points.len() as f64;
+    let mean_n = points.iter().map(|(n, _)| n).sum::<f64>() / count;
+    let mean_t = points.iter().map(|(_, t)| t).sum::<f64>() / count;
+
+    let covariance = points
+        .iter()
+        .map(|(n, t)| (n - mean_n) * (t - mean_t))
+        .sum::<f64>();
+    let variance = points.iter().map(|(n, _)| (n - mean_n).powi(2)).sum::<f64>();
+
+    if variance == 0.0 {
+        return None;
+    }
+
+    let slope = covariance / variance;
+    Some((mean_t - slope * mean_n, slope))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steady(count: usize, interval: f64) -> Vec<f64> {
+        (0..count).map(|i| i as f64 * interval).collect()
+    }
+
+    #[test]
+    fn needs_two_taps() {
+        assert_eq!(estimate(&[]), None);
+        assert_eq!(estimate(&[1.0]), None);
+    }
+
+    #[test]
+    fn steady_taps_give_exact_tempo() {
+        let estimate = estimate(&steady(8, 0.5)).unwrap();
+        assert!((estimate.bpm() - 120.0).abs() < 1e-9);
+        assert!(estimate.jitter < 1e-9);
+        assert!((estimate.confidence - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_a_sloppy_tap() {
+        let mut times = steady(8, 0.5);
+        times[4] += 0.2;
+
+        let estimate = estimate(&times).unwrap();
+        assert_eq!(estimate.taps_used, 7);
+        assert!((estimate.bpm() - 120.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn survives_a_missed_tap() {
+        let mut times = steady(8, 0.5);
+        times.remove(3);
+
+        let estimate = estimate(&times).unwrap();
+        assert!((estimate.bpm() - 120.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn shaky_taps_lower_confidence() {
+        let steady_taps = estimate(&steady(8, 0.5)).unwrap();
+
+        let shaky: Vec<f64> = steady(8, 0.5)
+            .iter()
+            .enumerate()
+            .map(|(i, t)| if i % 2 == 0 { t + 0.02 } else { t - 0.02 })
+            .collect();
+        let shaky_taps = estimate(&shaky).unwrap();
+
+        assert!(shaky_taps.jitter > steady_taps.jitter);
+        assert!(shaky_taps.confidence < steady_taps.confidence);
+        assert!((shaky_taps.bpm() - 120.0).abs() < 1.0);
+    }
+}
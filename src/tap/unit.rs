@@ -0,0 +1,88 @@
+use std::fmt;
+
+/// The note value being tapped. The metronome always counts quarter notes, so taps get
+/// converted to those.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TapUnit {
+    Half,
+    #[default]
+    Quarter,
+    Eighth,
+    DottedQuarter,
+    DottedEighth,
+    QuarterTriplet,
+    EighthTriplet,
+}
+
+impl TapUnit {
+    pub const ALL: [TapUnit; 7] = [
+        TapUnit::Half,
+        TapUnit::Quarter,
+        TapUnit::Eighth,
+        TapUnit::DottedQuarter,
+        TapUnit::DottedEighth,
+        TapUnit::QuarterTriplet,
+        TapUnit::EighthTriplet,
+    ];
+
+    /// How many quarter notes long one tap is.
+    pub fn quarters(self) -> f64 {
+        match self {
+            TapUnit::Half => 2.0,
+            TapUnit::Quarter => 1.0,
+            TapUnit::Eighth => 0.5,
+            TapUnit::DottedQuarter => 1.5,
+            TapUnit::DottedEighth => 0.75,
+            TapUnit::QuarterTriplet => 2.0 / 3.0,
+            TapUnit::EighthTriplet => 1.0 / 3.0,
+        }
+    }
+
+    /// The quarter-note tempo for taps coming at `tap_bpm`.
+    pub fn to_quarter_bpm(self, tap_bpm: f64) -> f64 {
+        tap_bpm * self.quarters()
+    }
+
+    pub fn next(self) -> TapUnit {
+        let index = TapUnit::ALL.iter().position(|u| *u == self).unwrap_or(0);
+        TapUnit::ALL[(index + 1) % TapUnit::ALL.len()]
+    }
+}
+
+impl fmt::Display for TapUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TapUnit::Half => "1/2",
+            TapUnit::Quarter => "1/4",
+            TapUnit::Eighth => "1/8",
+            TapUnit::DottedQuarter => "dotted 1/4",
+            TapUnit::DottedEighth => "dotted 1/8",
+            TapUnit::QuarterTriplet => "1/4 triplet",
+            TapUnit::EighthTriplet => "1/8 triplet",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_quarter_notes() {
+        // all of these are 120bpm in quarters
+        assert_eq!(TapUnit::Half.to_quarter_bpm(60.0), 120.0);
+        assert_eq!(TapUnit::Eighth.to_quarter_bpm(240.0), 120.0);
+        assert_eq!(TapUnit::DottedQuarter.to_quarter_bpm(80.0), 120.0);
+        assert!((TapUnit::EighthTriplet.to_quarter_bpm(360.0) - 120.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cycles_through_every_unit() {
+        let mut unit = TapUnit::default();
+        for _ in 0..TapUnit::ALL.len() {
+            unit = unit.next();
+        }
+        assert_eq!(unit, TapUnit::default());
+    }
+}
@@ -0,0 +1,144 @@
+// Crossfades and wipes from one sketch to the next, so a set can move between dailies without a
+// black gap. This is the timing; `mixer` puts the two pictures together.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::automation::Unit;
+use crate::named;
+use crate::tap::{self, clock::Clock};
+use crate::tween::ease::{Curve, Ease};
+use crate::tween::Tween;
+
+pub mod mixer;
+
+/// How the incoming sketch takes over from the outgoing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Kind {
+    /// Straight over, the moment it starts.
+    Cut,
+    #[default]
+    Fade,
+    /// The incoming picture's brightest parts come through first.
+    LumaWipe,
+    /// Pixel by pixel, in no particular order.
+    Dissolve,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 4] = [Kind::Cut, Kind::Fade, Kind::LumaWipe, Kind::Dissolve];
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Kind::Cut => "cut",
+            Kind::Fade => "fade",
+            Kind::LumaWipe => "luma",
+            Kind::Dissolve => "dissolve",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Kind, String> {
+        named::parse(&Kind::ALL, s, "transition")
+    }
+}
+
+/// A transition under way. It's timed in seconds, or in beats when it's lined up with the tap
+/// tempo, and `now` is always in the same unit as `start`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transition {
+    pub kind: Kind,
+    pub unit: Unit,
+    pub start: f64,
+    tween: Tween<f32>,
+}
+
+impl Transition {
+    pub fn new(kind: Kind, unit: Unit, start: f64, length: f64) -> Transition {
+        let length = match kind {
+            Kind::Cut => 0.0,
+            _ => length,
+        };
+        Transition {
+            kind,
+            unit,
+            start,
+            tween: Tween::new(0.0, 1.0, length).with_ease(Ease::InOut(Curve::Quad)),
+        }
+    }
+
+    /// Starting on the next bar and lasting one, given where the beat is now.
+    pub fn on_the_bar(kind: Kind, next_bar: f64, beats_per_bar: u64) -> Transition {
+        Transition::new(kind, Unit::Beats, next_bar, beats_per_bar as f64)
+    }
+
+    /// On the next bar of `metro` as it's drawn, so it lands with the picture rather than a
+    /// calibrated latency off it. Its `now` is `metro.visual_beats()`. `None` without a tempo.
+    pub fn on_the_bar_of<C: Clock>(kind: Kind, metro: &tap::Model<C>) -> Option<Transition> {
+        metro.bpm?;
+        let beats_per_bar = metro.meter().beats_per_bar;
+        Some(Transition::on_the_bar(kind, metro.next_visual_bar(), beats_per_bar))
+    }
+
+    /// How much of the incoming sketch shows at `now`: 0 until it starts, 1 once it's done.
+    pub fn progress(&self, now: f64) -> f32 {
+        if now < self.start {
+            return 0.0;
+        }
+        self.tween.value_at(now - self.start)
+    }
+
+    pub fn is_done(&self, now: f64) -> bool {
+        now >= self.start && self.tween.is_done(now - self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_from_start_to_end() {
+        let fade = Transition::new(Kind::Fade, Unit::Seconds, 10.0, 2.0);
+        assert_eq!(fade.progress(9.0), 0.0);
+        assert_eq!(fade.progress(11.0), 0.5);
+        assert!(!fade.is_done(11.0));
+        assert_eq!(fade.progress(13.0), 1.0);
+        assert!(fade.is_done(12.0));
+
+        // a cut waits for its start too, when it's on the bar
+        let cut = Transition::on_the_bar(Kind::Cut, 8.0, 4);
+        assert_eq!(cut.unit, Unit::Beats);
+        assert_eq!(cut.progress(7.9), 0.0);
+        assert!(!cut.is_done(7.9));
+        assert_eq!(cut.progress(8.0), 1.0);
+        assert!(cut.is_done(8.0));
+    }
+
+    #[test]
+    fn waits_for_the_bar_the_picture_is_on() {
+        let mut metro = tap::Model::default();
+        assert_eq!(Transition::on_the_bar_of(Kind::Fade, &metro), None);
+
+        // a fifth of a beat of latency puts the picture over the bar line the sound hasn't
+        // reached yet, so it waits for the one after
+        metro.latency = 0.1;
+        metro.sync(120.0, 3.9);
+        assert_eq!(metro.next_bar(), 4.0);
+        let fade = Transition::on_the_bar_of(Kind::Fade, &metro).unwrap();
+        assert_eq!(fade.start, 8.0);
+        assert_eq!(fade.progress(metro.visual_beats()), 0.0);
+        assert_eq!(fade.progress(10.0), 0.5);
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        named::assert_reads_back(&Kind::ALL, "wipe");
+    }
+}
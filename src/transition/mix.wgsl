@@ -0,0 +1,63 @@
+// Two canvases, mixed by however far the transition has got.
+
+struct Uniforms {
+    progress: f32,
+    // 0 fades (a cut is a fade that jumps), 1 is a luma wipe, 2 a dissolve
+    kind: u32,
+    // how soft the edge of a luma wipe is
+    softness: f32,
+    _padding: f32,
+};
+
+@group(0) @binding(0)
+var from_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var to_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var canvas_sampler: sampler;
+@group(0) @binding(3)
+var<uniform> uniforms: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// one triangle big enough to cover the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let x = f32((index << 1u) & 2u);
+    let y = f32(index & 2u);
+    var out: VertexOutput;
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.tex_coords = vec2<f32>(x, y);
+    return out;
+}
+
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+// 1 where `threshold` has been passed, with a soft edge that's fully on at the end
+fn reveal(threshold: f32, progress: f32, softness: f32) -> f32 {
+    let edge = progress * (1.0 + softness);
+    return 1.0 - smoothstep(edge - softness, edge, threshold);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let outgoing = textureSample(from_texture, canvas_sampler, vertex.tex_coords).rgb;
+    let incoming = textureSample(to_texture, canvas_sampler, vertex.tex_coords).rgb;
+
+    var amount = uniforms.progress;
+    if uniforms.kind == 1u {
+        amount = reveal(1.0 - luma(incoming), uniforms.progress, uniforms.softness);
+    } else if uniforms.kind == 2u {
+        amount = select(0.0, 1.0, hash(floor(vertex.position.xy)) < uniforms.progress);
+    }
+    return vec4<f32>(mix(outgoing, incoming, amount), 1.0);
+}
@@ -0,0 +1,50 @@
+use nannou::wgpu;
+
+use super::Kind;
+use crate::canvas::Pass;
+
+// how far behind the leading edge a luma wipe starts to show through
+const SOFTNESS: f32 = 0.1;
+
+/// Puts two canvases on screen at once, some way through a transition from one to the other.
+pub struct Mixer {
+    pass: Pass,
+}
+
+impl Mixer {
+    /// Ready to draw into textures like `format`, multisampled `sample_count` times: a window's
+    /// frame, usually.
+    pub fn new(device: &wgpu::Device, sample_count: u32, format: wgpu::TextureFormat) -> Mixer {
+        let shader = wgpu::include_wgsl!("mix.wgsl");
+        Mixer {
+            pass: Pass::new(device, shader, sample_count, format),
+        }
+    }
+
+    /// Draw `progress` of the way from `from` to `to` over the whole of `output`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        from: &wgpu::Texture,
+        to: &wgpu::Texture,
+        kind: Kind,
+        progress: f32,
+        output: &wgpu::TextureView,
+    ) {
+        // a cut is a fade that's only ever all or nothing
+        let kind: u32 = match kind {
+            Kind::Cut | Kind::Fade => 0,
+            Kind::LumaWipe => 1,
+            Kind::Dissolve => 2,
+        };
+        let mut uniforms = Vec::with_capacity(16);
+        uniforms.extend_from_slice(&progress.to_ne_bytes());
+        uniforms.extend_from_slice(&kind.to_ne_bytes());
+        uniforms.extend_from_slice(&SOFTNESS.to_ne_bytes());
+        uniforms.extend_from_slice(&0.0f32.to_ne_bytes());
+        self.pass
+            .encode(device, encoder, [from, to], &uniforms, output);
+    }
+}
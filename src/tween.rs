@@ -0,0 +1,73 @@
+use std::ops::{Add, Mul, Sub};
+
+pub mod ease;
+use ease::Ease;
+
+pub mod timeline;
+
+/// Anything that can be blended between two values: `f32`, `Vec2`, `Vec3`, linear colours...
+pub trait Tweenable:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self>
+{
+}
+
+impl<T> Tweenable for T where T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T> {}
+
+/// A move from one value to another over `duration`, which is in seconds or beats or whatever
+/// it's being played in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tween<T: Tweenable> {
+    pub from: T,
+    pub to: T,
+    pub duration: f64,
+    pub ease: Ease,
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub fn new(from: T, to: T, duration: f64) -> Self {
+        Tween {
+            from,
+            to,
+            duration,
+            ease: Ease::default(),
+        }
+    }
+
+    pub fn with_ease(self, ease: Ease) -> Self {
+        Tween { ease, ..self }
+    }
+
+    /// The value `time` into the tween. It holds still before the start and after the end.
+    pub fn value_at(&self, time: f64) -> T {
+        let progress = if self.duration > 0.0 {
+            time / self.duration
+        } else {
+            1.0
+        };
+        self.from + (self.to - self.from) * self.ease.apply(progress as f32)
+    }
+
+    pub fn is_done(&self, time: f64) -> bool {
+        time >= self.duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ease::Curve;
+    use nannou::geom::Vec2;
+
+    #[test]
+    fn tweens_between_values() {
+        let tween = Tween::new(Vec2::ZERO, Vec2::new(10.0, -10.0), 2.0).with_ease(Ease::LINEAR);
+        assert_eq!(tween.value_at(-1.0), Vec2::ZERO);
+        assert_eq!(tween.value_at(0.5), Vec2::new(2.5, -2.5));
+        assert_eq!(tween.value_at(3.0), Vec2::new(10.0, -10.0));
+        assert!(tween.is_done(2.0));
+
+        let eased = Tween::new(10.0, 20.0, 1.0).with_ease(Ease::In(Curve::Quad));
+        assert_eq!(eased.value_at(0.5), 12.5);
+        assert_eq!(Tween::new(1.0, 2.0, 0.0).value_at(0.0), 2.0);
+    }
+}
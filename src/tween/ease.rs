@@ -0,0 +1,290 @@
+use std::f32::consts::TAU;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::named;
+
+// how far back `Back` pulls before it goes, the usual ~10% overshoot
+const BACK_OVERSHOOT: f32 = 1.70158;
+// the wiggle in `Elastic`, in cycles per unit of progress
+const ELASTIC_PERIOD: f32 = 0.3;
+
+/// The shape of an easing curve, as it eases in. `Ease` turns it around for the other ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    Linear,
+    Quad,
+    Cubic,
+    Expo,
+    /// pulls back a little before going
+    Back,
+    /// winds up like a spring
+    Elastic,
+    /// bounces off the start a few times
+    Bounce,
+}
+
+impl Curve {
+    pub const ALL: [Curve; 7] = [
+        Curve::Linear,
+        Curve::Quad,
+        Curve::Cubic,
+        Curve::Expo,
+        Curve::Back,
+        Curve::Elastic,
+        Curve::Bounce,
+    ];
+
+    fn ease_in(self, t: f32) -> f32 {
+        match self {
+            Curve::Linear => t,
+            Curve::Quad => t * t,
+            Curve::Cubic => t * t * t,
+            Curve::Expo if t <= 0.0 => 0.0,
+            Curve::Expo => 2f32.powf(10.0 * (t - 1.0)),
+            Curve::Back => t * t * ((BACK_OVERSHOOT + 1.0) * t - BACK_OVERSHOOT),
+            Curve::Elastic if t <= 0.0 || t >= 1.0 => t,
+            Curve::Elastic => {
+                let wind = t - 1.0;
+                -(2f32.powf(10.0 * wind))
+                    * ((wind - ELASTIC_PERIOD / 4.0) * TAU / ELASTIC_PERIOD).sin()
+            }
+            Curve::Bounce => 1.0 - bounce_out(1.0 - t),
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Curve::Linear => "linear",
+            Curve::Quad => "quad",
+            Curve::Cubic => "cubic",
+            Curve::Expo => "expo",
+            Curve::Back => "back",
+            Curve::Elastic => "elastic",
+            Curve::Bounce => "bounce",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Curve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Curve, String> {
+        named::parse(&Curve::ALL, s, "curve")
+    }
+}
+
+// the classic four bounces, each a parabola a quarter the height of the last
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+/// How a tween gets from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ease {
+    /// Starts slow.
+    In(Curve),
+    /// Ends slow.
+    Out(Curve),
+    /// Slow at both ends.
+    InOut(Curve),
+    /// A CSS-style `cubic-bezier(x1, y1, x2, y2)`, for curves drawn in some other tool.
+    Bezier(f32, f32, f32, f32),
+}
+
+impl Default for Ease {
+    fn default() -> Ease {
+        Ease::InOut(Curve::Cubic)
+    }
+}
+
+impl Ease {
+    pub const LINEAR: Ease = Ease::In(Curve::Linear);
+
+    /// Where the tween is at `t` of the way through, where 0 is the start and 1 the end. Back
+    /// and elastic go past either end on the way.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::In(curve) => curve.ease_in(t),
+            Ease::Out(curve) => 1.0 - curve.ease_in(1.0 - t),
+            Ease::InOut(curve) if t < 0.5 => curve.ease_in(2.0 * t) / 2.0,
+            Ease::InOut(curve) => 1.0 - curve.ease_in(2.0 - 2.0 * t) / 2.0,
+            Ease::Bezier(x1, y1, x2, y2) => bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+/// "in cubic", "out back", "in-out expo", "linear" or "bezier 0.42 0 0.58 1".
+impl fmt::Display for Ease {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ease::In(Curve::Linear) | Ease::Out(Curve::Linear) | Ease::InOut(Curve::Linear) => {
+                f.write_str("linear")
+            }
+            Ease::In(curve) => write!(f, "in {}", curve),
+            Ease::Out(curve) => write!(f, "out {}", curve),
+            Ease::InOut(curve) => write!(f, "in-out {}", curve),
+            Ease::Bezier(x1, y1, x2, y2) => write!(f, "bezier {} {} {} {}", x1, y1, x2, y2),
+        }
+    }
+}
+
+impl FromStr for Ease {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Ease, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words[..] {
+            ["linear"] => Ok(Ease::LINEAR),
+            ["in", curve] => Ok(Ease::In(curve.parse()?)),
+            ["out", curve] => Ok(Ease::Out(curve.parse()?)),
+            ["in-out", curve] => Ok(Ease::InOut(curve.parse()?)),
+            ["bezier", x1, y1, x2, y2] => {
+                let number = |word: &str| {
+                    let number = word
+                        .parse::<f32>()
+                        .map_err(|e| format!("bad number in {:?}: {}", s, e))?;
+                    if !number.is_finite() {
+                        return Err(format!("{} in {:?} isn't finite", number, s));
+                    }
+                    Ok(number)
+                };
+                Ok(Ease::Bezier(
+                    number(x1)?,
+                    number(y1)?,
+                    number(x2)?,
+                    number(y2)?,
+                ))
+            }
+            _ => Err(format!("unknown ease {:?}", s)),
+        }
+    }
+}
+
+// one coordinate of a bezier from 0 to 1 with control points `a` and `b`
+fn cubic(a: f32, b: f32, s: f32) -> f32 {
+    let r = 1.0 - s;
+    3.0 * r * r * s * a + 3.0 * r * s * s * b + s * s * s
+}
+
+fn cubic_slope(a: f32, b: f32, s: f32) -> f32 {
+    let r = 1.0 - s;
+    3.0 * r * r * a + 6.0 * r * s * (b - a) + 3.0 * s * s * (1.0 - b)
+}
+
+// find where on the curve x is `t` and return the y there: Newton's method, falling back to
+// bisection where the curve is too flat for it
+fn bezier(x1: f32, y1: f32, x2: f32, y2: f32, t: f32) -> f32 {
+    let (x1, x2) = (x1.clamp(0.0, 1.0), x2.clamp(0.0, 1.0));
+    let mut s = t;
+    for _ in 0..8 {
+        let error = cubic(x1, x2, s) - t;
+        if error.abs() < 1e-6 {
+            return cubic(y1, y2, s);
+        }
+        let slope = cubic_slope(x1, x2, s);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        s -= error / slope;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    s = t;
+    for _ in 0..32 {
+        if cubic(x1, x2, s) < t {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+    cubic(y1, y2, s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_curve_starts_and_ends_in_place() {
+        for curve in Curve::ALL {
+            for ease in [Ease::In(curve), Ease::Out(curve), Ease::InOut(curve)] {
+                assert!(ease.apply(0.0).abs() < 1e-3, "{:?}", ease);
+                assert!((ease.apply(1.0) - 1.0).abs() < 1e-3, "{:?}", ease);
+            }
+            // in-out is symmetrical about the middle
+            let ease = Ease::InOut(curve);
+            assert!((ease.apply(0.5) - 0.5).abs() < 1e-3, "{:?}", curve);
+            assert!(
+                (ease.apply(0.2) + ease.apply(0.8) - 1.0).abs() < 1e-3,
+                "{:?}",
+                curve
+            );
+        }
+    }
+
+    #[test]
+    fn shapes() {
+        assert_eq!(Ease::In(Curve::Quad).apply(0.5), 0.25);
+        assert_eq!(Ease::Out(Curve::Cubic).apply(0.5), 0.875);
+        assert!(Ease::In(Curve::Back).apply(0.2) < 0.0);
+        assert!(Ease::Out(Curve::Elastic).apply(0.2) > 1.0);
+        // the top of the first bounce, and back down
+        assert!((Ease::Out(Curve::Bounce).apply(1.5 / 2.75) - 0.75).abs() < 1e-5);
+        assert!((Ease::Out(Curve::Bounce).apply(2.0 / 2.75) - 1.0).abs() < 1e-5);
+        // out of range is held at the ends
+        assert_eq!(Ease::LINEAR.apply(1.5), 1.0);
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        for ease in [
+            Ease::LINEAR,
+            Ease::In(Curve::Quad),
+            Ease::Out(Curve::Back),
+            Ease::InOut(Curve::Bounce),
+            Ease::Bezier(0.42, 0.0, 0.58, 1.0),
+        ] {
+            assert_eq!(ease.to_string().parse(), Ok(ease));
+        }
+        assert_eq!(Ease::InOut(Curve::Expo).to_string(), "in-out expo");
+        assert!("sideways cubic".parse::<Ease>().is_err());
+        assert!("in wobble".parse::<Ease>().is_err());
+        assert!("bezier 0 nan 1 1".parse::<Ease>().is_err());
+        assert!("bezier 0 0 inf 1".parse::<Ease>().is_err());
+        named::assert_reads_back(&Curve::ALL, "wobble");
+    }
+
+    #[test]
+    fn beziers_match_the_css_keywords() {
+        // cubic-bezier(0, 0, 1, 1) is linear
+        for t in [0.1, 0.5, 0.77] {
+            assert!((Ease::Bezier(0.0, 0.0, 1.0, 1.0).apply(t) - t).abs() < 1e-4);
+        }
+        // css `ease-in-out` is symmetrical, and slow to start
+        let css = Ease::Bezier(0.42, 0.0, 0.58, 1.0);
+        assert!((css.apply(0.5) - 0.5).abs() < 1e-4);
+        assert!((css.apply(0.25) + css.apply(0.75) - 1.0).abs() < 1e-4);
+        assert!(css.apply(0.25) < 0.25);
+        // control points outside 0..1 overshoot
+        assert!(Ease::Bezier(0.3, 1.5, 0.7, 1.0).apply(0.5) > 1.0);
+    }
+}
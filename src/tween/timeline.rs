@@ -0,0 +1,199 @@
+use super::{Tween, Tweenable};
+
+// plays a tween at a time relative to its start into the model
+type Play<M> = Box<dyn Fn(&mut M, f64)>;
+
+struct Clip<M> {
+    start: f64,
+    duration: f64,
+    play: Play<M>,
+}
+
+/// Tweens played one after another or side by side, each one setting something on a model `M`.
+/// Times are in whatever the clock passed to `update` counts: seconds, or beats from the tap
+/// tempo to play in time with the music.
+///
+/// ```ignore
+/// let sweep = Timeline::new()
+///     .then(Tween::new(60.0, 200.0, 4.0), |m: &mut Model, v| m.noise_scale = v)
+///     .with(Tween::new(1.0, 3.0, 2.0), |m, v| m.noise_strength = v)
+///     .then(Tween::new(200.0, 60.0, 4.0), |m, v| m.noise_scale = v);
+/// ```
+pub struct Timeline<M> {
+    clips: Vec<Clip<M>>,
+    // where the step being added to starts, and where everything so far finishes
+    cursor: f64,
+    end: f64,
+    start: Option<f64>,
+}
+
+impl<M> Default for Timeline<M> {
+    fn default() -> Self {
+        Timeline {
+            clips: Vec::new(),
+            cursor: 0.0,
+            end: 0.0,
+            start: None,
+        }
+    }
+}
+
+impl<M: 'static> Timeline<M> {
+    pub fn new() -> Self {
+        Timeline::default()
+    }
+
+    /// Play `tween` once everything so far has finished, handing each value to `set`.
+    pub fn then<T: Tweenable + 'static>(mut self, tween: Tween<T>, set: fn(&mut M, T)) -> Self {
+        self.cursor = self.end;
+        self.add(tween, set)
+    }
+
+    /// Play `tween` alongside the last thing added with `then`.
+    pub fn with<T: Tweenable + 'static>(self, tween: Tween<T>, set: fn(&mut M, T)) -> Self {
+        self.add(tween, set)
+    }
+
+    /// Do nothing for a while before whatever comes next.
+    pub fn wait(mut self, duration: f64) -> Self {
+        self.end += duration;
+        self.cursor = self.end;
+        self
+    }
+
+    fn add<T: Tweenable + 'static>(mut self, tween: Tween<T>, set: fn(&mut M, T)) -> Self {
+        self.end = self.end.max(self.cursor + tween.duration);
+        self.clips.push(Clip {
+            start: self.cursor,
+            duration: tween.duration,
+            play: Box::new(move |model, time| set(model, tween.value_at(time))),
+        });
+        self
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.end
+    }
+
+    pub fn start_at(&mut self, time: f64) {
+        self.start = Some(time);
+    }
+
+    /// Start on the next multiple of `quantum` after `now`, e.g. the next beat with a quantum
+    /// of 1 when the clock is in beats. Right now if `now` is already on one.
+    pub fn start_quantized(&mut self, now: f64, quantum: f64) {
+        let start = if quantum > 0.0 {
+            (now / quantum).ceil() * quantum
+        } else {
+            now
+        };
+        self.start_at(start);
+    }
+
+    pub fn is_finished(&self, now: f64) -> bool {
+        self.start.is_some_and(|start| now - start >= self.end)
+    }
+
+    /// Set everything to where the timeline leaves it, as if it had played right through. For
+    /// when the clock it's playing to stops or jumps and it would otherwise be left halfway.
+    pub fn finish(&self, model: &mut M) {
+        for clip in &self.clips {
+            (clip.play)(model, clip.duration);
+        }
+    }
+
+    /// Set everything that's playing at `now` on `model`. Tweens that haven't started yet leave
+    /// it alone; finished ones hold their last value until the whole timeline is done, so later
+    /// tweens of the same thing take over from them. Returns false once it's finished.
+    pub fn update(&self, model: &mut M, now: f64) -> bool {
+        let start = match self.start {
+            Some(start) if now >= start => start,
+            Some(_) => return true,
+            None => return false,
+        };
+        let time = now - start;
+        for clip in self.clips.iter().filter(|clip| clip.start <= time) {
+            (clip.play)(model, (time - clip.start).min(clip.duration));
+        }
+        time < self.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tween::ease::Ease;
+
+    #[derive(Default)]
+    struct Model {
+        scale: f32,
+        alpha: f32,
+    }
+
+    fn linear(from: f32, to: f32, duration: f64) -> Tween<f32> {
+        Tween::new(from, to, duration).with_ease(Ease::LINEAR)
+    }
+
+    fn timeline() -> Timeline<Model> {
+        Timeline::new()
+            .then(linear(0.0, 1.0, 2.0), |m: &mut Model, v| m.scale = v)
+            .with(linear(1.0, 0.0, 1.0), |m, v| m.alpha = v)
+            .wait(1.0)
+            .then(linear(1.0, 3.0, 2.0), |m, v| m.scale = v)
+    }
+
+    #[test]
+    fn plays_in_sequence_and_in_parallel() {
+        let mut timeline = timeline();
+        assert_eq!(timeline.duration(), 5.0);
+
+        let mut model = Model::default();
+        assert!(!timeline.update(&mut model, 0.0));
+        timeline.start_at(10.0);
+
+        assert!(timeline.update(&mut model, 10.5));
+        assert_eq!((model.scale, model.alpha), (0.25, 0.5));
+
+        // the alpha's done, the scale's still going
+        timeline.update(&mut model, 11.5);
+        assert_eq!((model.scale, model.alpha), (0.75, 0.0));
+
+        // waiting, then the second scale tween takes over from the first
+        timeline.update(&mut model, 12.5);
+        assert_eq!(model.scale, 1.0);
+        timeline.update(&mut model, 14.0);
+        assert_eq!(model.scale, 2.0);
+
+        assert!(!timeline.update(&mut model, 15.0));
+        assert_eq!(model.scale, 3.0);
+        assert!(timeline.is_finished(15.0));
+    }
+
+    #[test]
+    fn waits_for_the_next_beat() {
+        let mut timeline = timeline();
+        let mut model = Model {
+            scale: 7.0,
+            alpha: 7.0,
+        };
+        timeline.start_quantized(12.3, 4.0);
+        assert!(timeline.update(&mut model, 15.0));
+        assert_eq!((model.scale, model.alpha), (7.0, 7.0));
+        timeline.update(&mut model, 16.0);
+        assert_eq!((model.scale, model.alpha), (0.0, 1.0));
+
+        timeline.start_quantized(8.0, 4.0);
+        assert!(!timeline.is_finished(12.9));
+    }
+
+    #[test]
+    fn finishes_early_where_it_would_have_ended() {
+        let mut timeline = timeline();
+        let mut model = Model::default();
+        timeline.start_at(0.0);
+        timeline.update(&mut model, 0.5);
+
+        timeline.finish(&mut model);
+        assert_eq!((model.scale, model.alpha), (3.0, 0.0));
+    }
+}